@@ -0,0 +1,142 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::core::{
+    config::{DispatchLimits, LoadShedding},
+    stats::ServerStats,
+};
+
+/// Caps the number of requests dispatched to Swift at once, queueing the excess up to a bound.
+pub struct DispatchGate {
+    /// One permit per concurrent dispatch, or `None` when dispatches are not capped.
+    permits: Option<Semaphore>,
+    max_queued: usize,
+}
+
+/// Holds a dispatch slot, released when dropped.
+pub struct DispatchPermit<'a> {
+    _permit: Option<SemaphorePermit<'a>>,
+    stats: &'a ServerStats,
+}
+
+impl Drop for DispatchPermit<'_> {
+    fn drop(&mut self) {
+        self.stats
+            .in_flight_dispatches
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts a request waiting for a dispatch slot, until it gets one or its client goes away.
+struct QueuedRequest<'a> {
+    stats: &'a ServerStats,
+}
+
+impl Drop for QueuedRequest<'_> {
+    fn drop(&mut self) {
+        self.stats.queued_dispatches.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl DispatchGate {
+    pub fn new(limits: &DispatchLimits) -> DispatchGate {
+        DispatchGate {
+            permits: limits.max_concurrent.map(Semaphore::new),
+            max_queued: limits.max_queued,
+        }
+    }
+
+    /// Waits for a dispatch slot, or returns `None` when the queue is full.
+    pub async fn admit<'a>(&'a self, stats: &'a ServerStats) -> Option<DispatchPermit<'a>> {
+        let permit = match &self.permits {
+            None => None,
+            Some(permits) => match permits.try_acquire() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    let queued = stats.queued_dispatches.fetch_add(1, Ordering::Relaxed);
+                    let _queued = QueuedRequest { stats };
+                    if queued >= self.max_queued as u64 {
+                        return None;
+                    }
+
+                    // The semaphore is never closed.
+                    Some(permits.acquire().await.ok()?)
+                }
+            },
+        };
+
+        stats.in_flight_dispatches.fetch_add(1, Ordering::Relaxed);
+        return Some(DispatchPermit {
+            _permit: permit,
+            stats,
+        });
+    }
+}
+
+/// Rejects new requests early while Swift handlers fall behind, so that the admitted ones keep a bounded latency.
+///
+/// The latency a new request would see is estimated from the moving average of the dispatch latency,
+/// scaled by the number of requests queued per request in flight. Beyond the target, requests are
+/// rejected with a probability growing with the excess, so that some keep refreshing the average.
+pub struct LoadShedder {
+    target_latency: Option<Duration>,
+    /// The exponentially weighted moving average of the dispatch latency, in microseconds.
+    average_latency: AtomicU64,
+    tickets: AtomicU64,
+}
+
+impl LoadShedder {
+    pub fn new(config: &LoadShedding) -> LoadShedder {
+        LoadShedder {
+            target_latency: config.target_latency,
+            average_latency: AtomicU64::new(0),
+            tickets: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the latency of a completed or timed out dispatch.
+    pub fn record(&self, latency: Duration, stats: &ServerStats) {
+        let sample = latency.as_micros() as u64;
+        let average = self.average_latency.load(Ordering::Relaxed);
+        // Weighs each sample by 1/8. Concurrent updates may lose a sample, which the average tolerates.
+        let average = match average {
+            0 => sample,
+            average => average - average / 8 + sample / 8,
+        };
+        self.average_latency.store(average, Ordering::Relaxed);
+        stats
+            .average_dispatch_latency_us
+            .store(average, Ordering::Relaxed);
+    }
+
+    /// Whether a new request should be rejected.
+    pub fn should_shed(&self, stats: &ServerStats) -> bool {
+        let Some(target) = self.target_latency else {
+            return false;
+        };
+
+        // Without requests in flight, no completion would ever lower a stale average.
+        let in_flight = stats.in_flight_dispatches.load(Ordering::Relaxed);
+        if in_flight == 0 {
+            return false;
+        }
+
+        let queued = stats.queued_dispatches.load(Ordering::Relaxed);
+        let average = self.average_latency.load(Ordering::Relaxed) as f64;
+        let estimate = average * (1.0 + queued as f64 / in_flight as f64);
+        let target = target.as_micros() as f64;
+        if estimate <= target {
+            return false;
+        }
+
+        let probability = ((estimate - target) / target).min(1.0);
+        // Spreads the tickets evenly over [0, 1) with the golden ratio, instead of drawing random numbers.
+        let ticket = self.tickets.fetch_add(1, Ordering::Relaxed);
+        let draw = (ticket.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 11) as f64 / (1u64 << 53) as f64;
+        return draw < probability;
+    }
+}
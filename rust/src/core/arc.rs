@@ -1,5 +1,11 @@
 use std::sync::Arc;
 
+/// Returns a new `Arc` from a pointer that is owned by someone else (e.g. Swift),
+/// incrementing the strong count so that dropping the result leaves the owner's reference intact.
+///
+/// # Safety
+///
+/// `ptr` must have been obtained from `Arc::into_raw` and still be alive.
 pub unsafe fn arc_from_borrowed_ptr<T>(ptr: *const T) -> Arc<T> {
     unsafe {
         Arc::increment_strong_count(ptr);
@@ -0,0 +1,36 @@
+use hyper::{
+    Body,
+    body::{Bytes, HttpBody},
+};
+
+/// The most a body buffer reserves up front, whatever the `Content-Length`: larger bodies grow it as they arrive,
+/// so that clients cannot make the server reserve memory for bytes they never send.
+const MAX_INITIAL_CAPACITY: u64 = 64 * 1024;
+
+pub enum BodyError {
+    /// The body is larger than the limit, either as announced by `Content-Length` or once read.
+    TooLarge,
+    Read(hyper::Error),
+}
+
+/// Buffers `body`, failing as soon as it grows past `limit` bytes.
+/// Chunked bodies are counted while they are read, so no more than `limit` bytes plus one chunk are ever held.
+pub async fn read_limited(mut body: Body, limit: u64) -> Result<Bytes, BodyError> {
+    // The lower bound is the Content-Length when one was sent.
+    let announced = body.size_hint().lower();
+    if announced > limit {
+        return Err(BodyError::TooLarge);
+    }
+
+    let mut buffer = Vec::with_capacity(announced.min(MAX_INITIAL_CAPACITY) as usize);
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(BodyError::Read)?;
+        if (buffer.len() + chunk.len()) as u64 > limit {
+            return Err(BodyError::TooLarge);
+        }
+
+        buffer.extend_from_slice(&chunk);
+    }
+
+    return Ok(Bytes::from(buffer));
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    Body, Method, Request, StatusCode,
+    header::{AGE, AUTHORIZATION, CACHE_CONTROL, HeaderMap, HeaderValue, SET_COOKIE, VARY},
+};
+
+use crate::core::{config::ResponseCache, forwarded::ClientInfo};
+
+/// What identifies a cached response: the method and target of the request, the scheme and host the client
+/// requested, and the values of the headers of `ResponseCache::vary_headers`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    method: Method,
+    scheme: String,
+    host: Option<String>,
+    path_and_query: String,
+    varying: Vec<Option<HeaderValue>>,
+}
+
+struct Entry {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    stored: Instant,
+    expires: Instant,
+    /// The bytes the entry counts against `ResponseCache::max_size`.
+    size: usize,
+    /// When the entry was last used, as a position in `State::recency`.
+    used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<CacheKey, Entry>,
+    /// The keys of the entries, from the least to the most recently used.
+    recency: BTreeMap<u64, CacheKey>,
+    next_use: u64,
+    size: usize,
+}
+
+impl State {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.used);
+            self.size -= entry.size;
+        }
+    }
+
+    fn touch(&mut self, key: &CacheKey) -> u64 {
+        let used = self.next_use;
+        self.next_use += 1;
+        self.recency.insert(used, key.clone());
+        return used;
+    }
+}
+
+/// A response served from the cache.
+pub struct CachedResponse {
+    pub status: StatusCode,
+    /// The headers of the handler response, with the `Age` of the entry.
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// The responses of one `ResponseCache`.
+pub struct Cache {
+    config: ResponseCache,
+    state: Mutex<State>,
+}
+
+impl Cache {
+    pub fn new(config: ResponseCache) -> Cache {
+        Cache {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// The key of `request` from `client`, or `None` when its response must not be shared with other clients:
+    /// methods other than GET, and authorized requests.
+    pub fn key(&self, request: &Request<Body>, client: &ClientInfo) -> Option<CacheKey> {
+        if request.method() != Method::GET || request.headers().contains_key(AUTHORIZATION) {
+            return None;
+        }
+
+        return Some(CacheKey {
+            method: request.method().clone(),
+            // Handlers see the scheme and host, so responses for different hosts are not shared.
+            scheme: client.scheme.clone(),
+            host: client.host.clone(),
+            path_and_query: request
+                .uri()
+                .path_and_query()
+                .map(|path_and_query| path_and_query.as_str().to_string())
+                .unwrap_or_default(),
+            varying: self
+                .config
+                .vary_headers
+                .iter()
+                .map(|name| request.headers().get(name).cloned())
+                .collect(),
+        });
+    }
+
+    /// The fresh response cached for `key`, if any.
+    pub fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        let entry = state.entries.get(key)?;
+        if entry.expires <= now {
+            state.remove(key);
+            return None;
+        }
+
+        let previous_use = entry.used;
+        state.recency.remove(&previous_use);
+        let used = state.touch(key);
+        let entry = state.entries.get_mut(key)?;
+        entry.used = used;
+
+        let mut headers = entry.headers.clone();
+        headers.insert(AGE, now.duration_since(entry.stored).as_secs().into());
+        return Some(CachedResponse {
+            status: entry.status,
+            headers,
+            body: entry.body.clone(),
+        });
+    }
+
+    /// Caches the response of a handler for `key`, when it is a 200 whose `Cache-Control` allows a shared
+    /// cache to store it, that sets no cookie, and that varies only on `ResponseCache::vary_headers`.
+    /// The least recently used entries are evicted to make room for it.
+    pub fn insert(&self, key: CacheKey, status: StatusCode, headers: &HeaderMap, body: &[u8]) {
+        if status != StatusCode::OK
+            || headers.contains_key(SET_COOKIE)
+            || !self.varies_on_key(headers)
+        {
+            return;
+        }
+
+        let Some(ttl) = self.ttl(headers) else {
+            return;
+        };
+
+        let size = body.len()
+            + key.path_and_query.len()
+            + key.host.as_ref().map_or(0, |host| host.len())
+            + headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>();
+        if size > self.config.max_size {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.remove(&key);
+
+        // Expired entries go first, then the least recently used ones.
+        let now = Instant::now();
+        if state.size + size > self.config.max_size {
+            let expired: Vec<CacheKey> = state
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.expires <= now)
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in &expired {
+                state.remove(key);
+            }
+        }
+        while state.size + size > self.config.max_size {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = state.entries.remove(&oldest) {
+                state.size -= entry.size;
+            }
+        }
+
+        let used = state.touch(&key);
+        state.size += size;
+        state.entries.insert(
+            key,
+            Entry {
+                status,
+                headers: headers.clone(),
+                body: body.to_vec(),
+                stored: now,
+                expires: now + ttl,
+                size,
+                used,
+            },
+        );
+    }
+
+    /// How long a response with `headers` stays fresh: `s-maxage`, then `max-age`, then
+    /// `ResponseCache::default_ttl`, within `ResponseCache::max_ttl`. `None` when it must not be stored.
+    fn ttl(&self, headers: &HeaderMap) -> Option<Duration> {
+        let mut max_age = None;
+        let mut shared_max_age = None;
+
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let seconds = || value.and_then(|value| value.parse::<u64>().ok());
+
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" | "no-cache" | "private" => return None,
+                "max-age" => max_age = seconds(),
+                "s-maxage" => shared_max_age = seconds(),
+                _ => {}
+            }
+        }
+
+        let ttl = shared_max_age
+            .or(max_age)
+            .map(Duration::from_secs)
+            .or(self.config.default_ttl)?
+            .min(self.config.max_ttl);
+        return (!ttl.is_zero()).then_some(ttl);
+    }
+
+    /// Whether every header the response varies on is part of the key.
+    fn varies_on_key(&self, headers: &HeaderMap) -> bool {
+        return headers
+            .get_all(VARY)
+            .iter()
+            // An unreadable value could name anything, as `*` does.
+            .map(|value| value.to_str().unwrap_or("*"))
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .all(|name| {
+                self.config
+                    .vary_headers
+                    .iter()
+                    .any(|vary_header| vary_header.as_str().eq_ignore_ascii_case(name))
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hyper::{
+        Body, Request, StatusCode,
+        header::{AUTHORIZATION, CACHE_CONTROL, HeaderMap, HeaderName, HeaderValue, VARY},
+    };
+
+    use super::Cache;
+    use crate::core::{config::ResponseCache, forwarded::ClientInfo};
+
+    fn client(scheme: &str, host: &str) -> ClientInfo {
+        return ClientInfo {
+            address: None,
+            scheme: scheme.to_string(),
+            host: Some(host.to_string()),
+        };
+    }
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut request = Request::get(path);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        return request.body(Body::empty()).expect("request");
+    }
+
+    fn cacheable(max_age: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let value = format!("public, max-age={}", max_age);
+        headers.insert(CACHE_CONTROL, HeaderValue::from_str(&value).expect("value"));
+        return headers;
+    }
+
+    #[test]
+    fn responses_are_cached_per_scheme_and_host() {
+        let cache = Cache::new(ResponseCache::default());
+        let request = request("/page?x=1", &[]);
+        let a = cache
+            .key(&request, &client("https", "a.example"))
+            .expect("key");
+        let b = cache
+            .key(&request, &client("https", "b.example"))
+            .expect("key");
+        let plain = cache
+            .key(&request, &client("http", "a.example"))
+            .expect("key");
+        assert_ne!(a, b);
+        assert_ne!(a, plain);
+
+        cache.insert(a.clone(), StatusCode::OK, &cacheable("60"), b"a");
+        cache.insert(b.clone(), StatusCode::OK, &cacheable("60"), b"b");
+        assert_eq!(cache.get(&a).expect("cached").body, b"a");
+        assert_eq!(cache.get(&b).expect("cached").body, b"b");
+        assert!(cache.get(&plain).is_none());
+    }
+
+    #[test]
+    fn authorized_and_non_get_requests_are_not_cached() {
+        let cache = Cache::new(ResponseCache::default());
+        let client = client("https", "a.example");
+        assert!(
+            cache
+                .key(
+                    &request("/", &[(AUTHORIZATION.as_str(), "Bearer x")]),
+                    &client
+                )
+                .is_none()
+        );
+
+        let post = Request::post("/").body(Body::empty()).expect("request");
+        assert!(cache.key(&post, &client).is_none());
+    }
+
+    #[test]
+    fn vary_headers_separate_entries() {
+        let accept_language = HeaderName::from_static("accept-language");
+        let cache = Cache::new(ResponseCache {
+            vary_headers: vec![accept_language.clone()],
+            ..ResponseCache::default()
+        });
+        let client = client("https", "a.example");
+        let english = cache
+            .key(&request("/", &[("accept-language", "en")]), &client)
+            .expect("key");
+        let french = cache
+            .key(&request("/", &[("accept-language", "fr")]), &client)
+            .expect("key");
+        assert_ne!(english, french);
+
+        let mut headers = cacheable("60");
+        headers.insert(VARY, HeaderValue::from_static("Accept-Language"));
+        cache.insert(english.clone(), StatusCode::OK, &headers, b"hello");
+        assert_eq!(cache.get(&english).expect("cached").body, b"hello");
+        assert!(cache.get(&french).is_none());
+
+        // A response varying on a header outside the key is not stored.
+        headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+        cache.insert(french.clone(), StatusCode::OK, &headers, b"bonjour");
+        assert!(cache.get(&french).is_none());
+    }
+
+    #[test]
+    fn uncacheable_responses_are_not_stored() {
+        let cache = Cache::new(ResponseCache::default());
+        let key = cache
+            .key(&request("/", &[]), &client("https", "a.example"))
+            .expect("key");
+
+        cache.insert(key.clone(), StatusCode::NOT_FOUND, &cacheable("60"), b"");
+        let mut private = HeaderMap::new();
+        private.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("private, max-age=60"),
+        );
+        cache.insert(key.clone(), StatusCode::OK, &private, b"");
+        // Without `max-age` nor a default TTL, nothing says how long the response stays fresh.
+        cache.insert(key.clone(), StatusCode::OK, &HeaderMap::new(), b"");
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let cache = Cache::new(ResponseCache {
+            default_ttl: Some(Duration::from_secs(60)),
+            max_ttl: Duration::from_millis(50),
+            ..ResponseCache::default()
+        });
+        let key = cache
+            .key(&request("/", &[]), &client("https", "a.example"))
+            .expect("key");
+
+        cache.insert(key.clone(), StatusCode::OK, &HeaderMap::new(), b"fresh");
+        let cached = cache.get(&key).expect("cached");
+        assert_eq!(cached.body, b"fresh");
+        assert_eq!(cached.headers.get("age").expect("age"), "0");
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_beyond_the_max_size() {
+        let cache = Cache::new(ResponseCache {
+            max_size: 300,
+            ..ResponseCache::default()
+        });
+        let client = client("https", "a.example");
+        let key = |path: &str| cache.key(&request(path, &[]), &client).expect("key");
+        let body = [0u8; 100];
+
+        cache.insert(key("/1"), StatusCode::OK, &cacheable("60"), &body);
+        cache.insert(key("/2"), StatusCode::OK, &cacheable("60"), &body);
+        assert!(cache.get(&key("/1")).is_some());
+        cache.insert(key("/3"), StatusCode::OK, &cacheable("60"), &body);
+
+        assert!(cache.get(&key("/1")).is_some());
+        assert!(cache.get(&key("/2")).is_none());
+        assert!(cache.get(&key("/3")).is_some());
+
+        // A response larger than the whole cache is never stored.
+        cache.insert(key("/4"), StatusCode::OK, &cacheable("60"), &[0u8; 400]);
+        assert!(cache.get(&key("/4")).is_none());
+        assert!(cache.get(&key("/3")).is_some());
+    }
+}
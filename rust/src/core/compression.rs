@@ -0,0 +1,364 @@
+use std::io::{self, Read, Write};
+
+use hyper::{
+    Body, Response,
+    header::{
+        CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+        HeaderMap, HeaderValue, VARY,
+    },
+};
+
+use crate::core::config::Compression;
+
+const GZIP_LEVEL: u32 = 6;
+const BROTLI_QUALITY: u32 = 4;
+const BROTLI_WINDOW: u32 = 22;
+const ZSTD_LEVEL: i32 = 3;
+
+/// A content coding the server can compress responses and decode request bodies with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    /// The zlib format, as specified for `deflate` by HTTP.
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    /// The token of the encoding in `Accept-Encoding` and `Content-Encoding`.
+    pub fn token(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    pub fn from_token(token: &str) -> Option<Encoding> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            "br" => Some(Encoding::Brotli),
+            "zstd" => Some(Encoding::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::with_capacity(data.len() / 2),
+                    flate2::Compression::new(GZIP_LEVEL),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(
+                    Vec::with_capacity(data.len() / 2),
+                    flate2::Compression::new(GZIP_LEVEL),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut out = Vec::with_capacity(data.len() / 2);
+                {
+                    let mut encoder = brotli::CompressorWriter::new(
+                        &mut out,
+                        4096,
+                        BROTLI_QUALITY,
+                        BROTLI_WINDOW,
+                    );
+                    encoder.write_all(data)?;
+                }
+                Ok(out)
+            }
+            Encoding::Zstd => zstd::encode_all(data, ZSTD_LEVEL),
+        }
+    }
+
+    /// Decodes `data`, failing as soon as the decoded bytes grow past `limit`.
+    pub fn decompress(&self, data: &[u8], limit: u64) -> Result<Vec<u8>, DecompressError> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Encoding::Gzip => Box::new(flate2::read::MultiGzDecoder::new(data)),
+            Encoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(data)),
+            Encoding::Brotli => Box::new(brotli::Decompressor::new(data, 4096)),
+            Encoding::Zstd => Box::new(
+                zstd::stream::read::Decoder::new(data).map_err(|_| DecompressError::Invalid)?,
+            ),
+        };
+
+        // Reading one byte past the limit tells a body of exactly `limit` bytes from a larger one.
+        let mut out = Vec::new();
+        decoder
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut out)
+            .map_err(|_| DecompressError::Invalid)?;
+        if out.len() as u64 > limit {
+            return Err(DecompressError::TooLarge);
+        }
+
+        return Ok(out);
+    }
+}
+
+pub enum DecompressError {
+    /// The decoded body is larger than the limit.
+    TooLarge,
+    /// The body is not valid for its encoding.
+    Invalid,
+}
+
+/// Parses the codings of a `Content-Encoding` header, in the order they were applied.
+/// Fails with the first coding the server cannot decode.
+pub fn content_codings(content_encoding: &str) -> Result<Vec<Encoding>, String> {
+    return content_encoding
+        .split(',')
+        .map(|coding| coding.trim())
+        .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+        .map(|coding| Encoding::from_token(coding).ok_or_else(|| coding.to_string()))
+        .collect();
+}
+
+/// Picks the encoding of `preferred` with the highest quality in `accept_encoding`,
+/// the server's order breaking ties. Returns `None` when the client accepts none of them.
+pub fn negotiate(accept_encoding: &str, preferred: &[Encoding]) -> Option<Encoding> {
+    let mut wildcard = None;
+    let mut qualities: Vec<(Encoding, f32)> = Vec::new();
+
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let token = parts.next().unwrap_or_default().trim();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if token == "*" {
+            wildcard = Some(quality);
+        } else if let Some(encoding) = Encoding::from_token(token) {
+            qualities.push((encoding, quality));
+        }
+    }
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for encoding in preferred {
+        // Encodings the client did not list are acceptable with the quality of `*`, if any.
+        let quality = qualities
+            .iter()
+            .find(|(listed, _)| listed == encoding)
+            .map(|(_, quality)| *quality)
+            .or(wildcard)
+            .unwrap_or(0.0);
+
+        if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((*encoding, quality));
+        }
+    }
+
+    return best.map(|(encoding, _)| encoding);
+}
+
+/// Whether `content_type` matches one of `patterns`: `text/` matches a type prefix,
+/// `+json` a structured syntax suffix, and anything else the exact media type.
+fn matches_content_type(content_type: &str, patterns: &[String]) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    return patterns.iter().any(|pattern| {
+        if pattern.ends_with('/') {
+            media_type.starts_with(pattern.as_str())
+        } else if pattern.starts_with('+') {
+            media_type.ends_with(pattern.as_str())
+        } else {
+            media_type == *pattern
+        }
+    });
+}
+
+/// Whether the representation of `headers` with a `body_len` bytes body may be compressed,
+/// regardless of the encodings accepted by the client.
+fn is_compressible(
+    status: hyper::StatusCode,
+    headers: &HeaderMap,
+    body_len: usize,
+    config: &Compression,
+) -> bool {
+    if status.is_informational()
+        || status == hyper::StatusCode::NO_CONTENT
+        || status == hyper::StatusCode::NOT_MODIFIED
+        || body_len < config.min_size
+    {
+        return false;
+    }
+
+    // Already encoded and partial representations are sent as they are.
+    if headers.contains_key(CONTENT_ENCODING) || headers.contains_key(CONTENT_RANGE) {
+        return false;
+    }
+
+    let no_transform = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-transform"));
+    if no_transform {
+        return false;
+    }
+
+    return headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| matches_content_type(content_type, &config.content_types));
+}
+
+/// Adds `Accept-Encoding` to the `Vary` header, unless it already varies on it or on everything.
+fn vary_on_accept_encoding(headers: &mut HeaderMap) {
+    let varies = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|name| {
+            let name = name.trim();
+            name == "*" || name.eq_ignore_ascii_case("accept-encoding")
+        });
+
+    if !varies {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+/// Compresses the `body` of `response` with the best encoding accepted by the client, when its
+/// content type and size make it eligible, and sets the body of the response.
+/// Eligible responses vary on `Accept-Encoding` even when sent uncompressed.
+pub fn apply(
+    response: &mut Response<Body>,
+    body: Vec<u8>,
+    accept_encoding: Option<&str>,
+    config: &Compression,
+) {
+    if !is_compressible(response.status(), response.headers(), body.len(), config) {
+        *response.body_mut() = Body::from(body);
+        return;
+    }
+
+    vary_on_accept_encoding(response.headers_mut());
+
+    let encoding = accept_encoding.and_then(|accept| negotiate(accept, &config.encodings));
+    let compressed = encoding.and_then(|encoding| {
+        let compressed = encoding.compress(&body).ok()?;
+        // Incompressible data is not worth the client decoding it.
+        (compressed.len() < body.len()).then_some((encoding, compressed))
+    });
+
+    let Some((encoding, compressed)) = compressed else {
+        *response.body_mut() = Body::from(body);
+        return;
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.token()));
+    headers.remove(CONTENT_LENGTH);
+
+    // The encoded bytes differ from the identity ones, so a strong validator no longer applies.
+    if let Some(etag) = headers.get(ETAG)
+        && !etag.as_bytes().starts_with(b"W/")
+        && let Ok(weak) = HeaderValue::from_bytes(&[b"W/", etag.as_bytes()].concat())
+    {
+        headers.insert(ETAG, weak);
+    }
+
+    *response.body_mut() = Body::from(compressed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Encoding, negotiate};
+
+    const ALL: &[Encoding] = &[
+        Encoding::Zstd,
+        Encoding::Brotli,
+        Encoding::Gzip,
+        Encoding::Deflate,
+    ];
+
+    #[test]
+    fn the_highest_quality_wins() {
+        assert_eq!(negotiate("gzip;q=0.5, br", ALL), Some(Encoding::Brotli));
+        assert_eq!(negotiate("br;q=0.2, gzip;q=0.8", ALL), Some(Encoding::Gzip));
+        assert_eq!(negotiate("GZIP ; q=1.0", ALL), Some(Encoding::Gzip));
+        assert_eq!(negotiate("x-gzip", ALL), Some(Encoding::Gzip));
+        // Unparsable qualities count as 1.
+        assert_eq!(
+            negotiate("gzip;q=high, br;q=0.5", ALL),
+            Some(Encoding::Gzip)
+        );
+    }
+
+    #[test]
+    fn ties_are_broken_by_the_server_preference() {
+        assert_eq!(negotiate("gzip, br, zstd", ALL), Some(Encoding::Zstd));
+        assert_eq!(
+            negotiate("gzip;q=0.5, br;q=0.5", ALL),
+            Some(Encoding::Brotli)
+        );
+        let gzip_first = [Encoding::Gzip, Encoding::Brotli];
+        assert_eq!(negotiate("br, gzip", &gzip_first), Some(Encoding::Gzip));
+    }
+
+    #[test]
+    fn zero_quality_excludes_an_encoding() {
+        assert_eq!(negotiate("br;q=0, gzip;q=0.1", ALL), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip;q=0", ALL), None);
+        assert_eq!(negotiate("*, zstd;q=0, br;q=0", ALL), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip", &[Encoding::Brotli]), None);
+    }
+
+    #[test]
+    fn the_wildcard_covers_the_encodings_not_listed() {
+        assert_eq!(negotiate("*", ALL), Some(Encoding::Zstd));
+        assert_eq!(negotiate("gzip, *;q=0.5", ALL), Some(Encoding::Gzip));
+        assert_eq!(negotiate("*;q=0", ALL), None);
+        assert_eq!(negotiate("gzip;q=0.1, *;q=0", ALL), Some(Encoding::Gzip));
+    }
+
+    #[test]
+    fn identity_is_never_negotiated() {
+        assert_eq!(negotiate("identity", ALL), None);
+        assert_eq!(negotiate("identity;q=0", ALL), None);
+        assert_eq!(
+            negotiate("identity;q=0, gzip;q=0.1", ALL),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(negotiate("", ALL), None);
+    }
+
+    #[test]
+    fn deflate_is_offered_and_round_trips() {
+        assert_eq!(negotiate("deflate", ALL), Some(Encoding::Deflate));
+        assert_eq!(
+            negotiate("gzip;q=0.5, deflate", ALL),
+            Some(Encoding::Deflate)
+        );
+
+        let data = b"hello hello hello hello hello".repeat(10);
+        let compressed = Encoding::Deflate.compress(&data).expect("compress");
+        // The zlib format starts with its CMF byte, 8 being deflate.
+        assert_eq!(compressed[0] & 0x0f, 8);
+        let decompressed = Encoding::Deflate
+            .decompress(&compressed, data.len() as u64)
+            .ok()
+            .expect("decompress");
+        assert_eq!(decompressed, data);
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use hyper::header::HeaderName;
+
+use crate::core::{
+    compression::Encoding, cors::Cors, forwarded::Cidr, rate_limit::RateLimit, types::Port,
+};
+
+/// The address of a listener.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenerAddress {
+    Tcp(SocketAddr),
+    /// A Unix domain socket bound to a filesystem path.
+    Unix(PathBuf),
+    /// A Unix domain socket in the Linux abstract namespace, identified by its name without the leading NUL.
+    UnixAbstract(Vec<u8>),
+}
+
+impl fmt::Display for ListenerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenerAddress::Tcp(address) => write!(f, "{}", address),
+            ListenerAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+            ListenerAddress::UnixAbstract(name) => {
+                write!(f, "unix:@{}", String::from_utf8_lossy(name))
+            }
+        }
+    }
+}
+
+/// PEM-encoded data, either read from a file when the server starts (and when certificates are reloaded),
+/// or provided in memory.
+#[derive(Clone)]
+pub enum PemSource {
+    File(PathBuf),
+    Memory(Vec<u8>),
+}
+
+impl fmt::Display for PemSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PemSource::File(path) => write!(f, "{}", path.display()),
+            PemSource::Memory(_) => write!(f, "in-memory PEM"),
+        }
+    }
+}
+
+impl fmt::Debug for PemSource {
+    // Never print in-memory PEM data, which may hold a private key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PemSource({})", self)
+    }
+}
+
+/// A TLS certificate chain and its private key.
+#[derive(Clone, Debug)]
+pub struct CertificateConfig {
+    /// The server names (SNI) the certificate is selected for, e.g. `example.com` or `*.example.com`.
+    /// A certificate without names is the default, served when no name matches.
+    pub server_names: Vec<String>,
+    pub chain: PemSource,
+    pub key: PemSource,
+}
+
+/// Configuration of a single address the server listens on.
+#[derive(Clone, Debug)]
+pub struct ListenerConfig {
+    pub address: ListenerAddress,
+    /// Whether an IPv6 listener only accepts IPv6 connections.
+    /// Defaults to `false`, so that binding `[::]` serves both IPv4 and IPv6 (dual-stack).
+    pub ipv6_only: bool,
+    /// The permission bits applied to a filesystem Unix socket once bound (e.g. `0o660`).
+    /// When `None`, the socket keeps the permissions derived from the process umask.
+    pub unix_mode: Option<u32>,
+    /// Whether a filesystem Unix socket left behind by a previous process is removed before binding.
+    /// A socket that still accepts connections is never removed.
+    pub remove_stale_socket: bool,
+    /// The certificates served by the listener. TLS is enabled when at least one is configured.
+    pub certificates: Vec<CertificateConfig>,
+    /// Whether connections start with a PROXY protocol v1 or v2 header, sent by a load balancer to
+    /// describe the client. Connections without a valid header are dropped.
+    pub proxy_protocol: bool,
+}
+
+impl ListenerConfig {
+    pub fn new(address: ListenerAddress) -> ListenerConfig {
+        ListenerConfig {
+            address,
+            ipv6_only: false,
+            unix_mode: None,
+            remove_stale_socket: true,
+            certificates: Vec::new(),
+            proxy_protocol: false,
+        }
+    }
+
+    pub fn tcp(address: SocketAddr) -> ListenerConfig {
+        ListenerConfig::new(ListenerAddress::Tcp(address))
+    }
+}
+
+/// Configuration of the Tokio runtime that serves the requests of a server.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// Runs every task on the server thread instead of a pool of workers.
+    pub current_thread: bool,
+    /// The number of worker threads of the multi-thread runtime. Defaults to the number of CPU cores.
+    pub worker_threads: Option<usize>,
+    /// The maximum number of threads spawned for blocking work. Defaults to Tokio's limit.
+    pub max_blocking_threads: Option<usize>,
+    /// The prefix of the names of the threads spawned by the server, suffixed with their index.
+    pub thread_name_prefix: Option<String>,
+}
+
+/// Limits applied to every request before it is dispatched to Swift.
+#[derive(Clone, Debug)]
+pub struct RequestLimits {
+    /// The maximum size in bytes of a request body. Larger bodies are answered with 413 Payload Too Large.
+    pub max_body_size: u64,
+    /// The maximum length in bytes of the request target (path and query). Longer ones are answered with 414 URI Too Long.
+    pub max_uri_length: usize,
+    /// The maximum size in bytes of the header names and values of a request, answered with 431 beyond it.
+    pub max_header_bytes: usize,
+    /// The maximum number of header fields of a request, answered with 431 beyond it.
+    /// HTTP/1 connections never accept more than 100 header fields.
+    pub max_header_count: usize,
+    /// How long a client has to send the whole body of a request once its head was read.
+    /// Slower bodies are answered with 408 Request Timeout.
+    pub body_read_timeout: Option<Duration>,
+}
+
+impl RequestLimits {
+    pub(crate) const DEFAULT_MAX_BODY_SIZE: u64 = 2 * 1024 * 1024;
+    pub(crate) const DEFAULT_MAX_URI_LENGTH: usize = 8 * 1024;
+    pub(crate) const DEFAULT_MAX_HEADER_BYTES: usize = 32 * 1024;
+    pub(crate) const DEFAULT_MAX_HEADER_COUNT: usize = 100;
+    pub(crate) const DEFAULT_BODY_READ_TIMEOUT: Duration = Duration::from_secs(60);
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_body_size: RequestLimits::DEFAULT_MAX_BODY_SIZE,
+            max_uri_length: RequestLimits::DEFAULT_MAX_URI_LENGTH,
+            max_header_bytes: RequestLimits::DEFAULT_MAX_HEADER_BYTES,
+            max_header_count: RequestLimits::DEFAULT_MAX_HEADER_COUNT,
+            body_read_timeout: Some(RequestLimits::DEFAULT_BODY_READ_TIMEOUT),
+        }
+    }
+}
+
+/// Limits on the lifetime of a connection, which close it once exceeded.
+#[derive(Clone, Debug)]
+pub struct ConnectionLimits {
+    /// How long a client has to send the head of a request once it sent its first byte.
+    /// Protects against clients trickling headers to hold connections open (slowloris). HTTP/1 only.
+    pub header_read_timeout: Option<Duration>,
+    /// How long a connection stays open without any request in flight.
+    pub idle_timeout: Option<Duration>,
+    /// The number of requests served on a connection before it is gracefully closed.
+    pub max_requests: Option<u64>,
+    /// The number of connections open at once across all listeners. Beyond it, the server
+    /// stops accepting until a connection closes, leaving new ones in the listen backlog.
+    pub max_connections: Option<usize>,
+}
+
+impl ConnectionLimits {
+    pub(crate) const DEFAULT_HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+    pub(crate) const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        ConnectionLimits {
+            header_read_timeout: Some(ConnectionLimits::DEFAULT_HEADER_READ_TIMEOUT),
+            idle_timeout: Some(ConnectionLimits::DEFAULT_IDLE_TIMEOUT),
+            max_requests: None,
+            max_connections: None,
+        }
+    }
+}
+
+/// Limits on the requests handled by Swift at once.
+#[derive(Clone, Debug)]
+pub struct DispatchLimits {
+    /// The number of requests dispatched to Swift at once. Uncapped when `None`.
+    pub max_concurrent: Option<usize>,
+    /// The number of requests waiting for a dispatch slot once all are taken.
+    /// Beyond it, requests are answered with 503 Service Unavailable.
+    pub max_queued: usize,
+}
+
+impl DispatchLimits {
+    pub(crate) const DEFAULT_MAX_QUEUED: usize = 1024;
+}
+
+impl Default for DispatchLimits {
+    fn default() -> Self {
+        DispatchLimits {
+            max_concurrent: None,
+            max_queued: DispatchLimits::DEFAULT_MAX_QUEUED,
+        }
+    }
+}
+
+/// Adaptive load shedding, which rejects new requests while Swift handlers fall behind.
+#[derive(Clone, Debug)]
+pub struct LoadShedding {
+    /// The latency a request should see in Swift, including the time it waits for a dispatch slot.
+    /// Requests expected to exceed it are rejected with 503. Shedding is disabled when `None`.
+    pub target_latency: Option<Duration>,
+    /// The delay sent to rejected clients in the `Retry-After` header, rounded up to seconds.
+    pub retry_after: Duration,
+}
+
+impl LoadShedding {
+    pub(crate) const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+}
+
+impl Default for LoadShedding {
+    fn default() -> Self {
+        LoadShedding {
+            target_latency: None,
+            retry_after: LoadShedding::DEFAULT_RETRY_AFTER,
+        }
+    }
+}
+
+/// Compression of the responses whose content type and size make them worth it,
+/// with the encodings negotiated from `Accept-Encoding`.
+#[derive(Clone, Debug)]
+pub struct Compression {
+    /// The encodings the server offers, by order of preference.
+    pub encodings: Vec<Encoding>,
+    /// The compressible media types: `text/` matches a type prefix, `+json` a suffix,
+    /// and anything else the exact media type. Responses without a `Content-Type` are not compressed.
+    pub content_types: Vec<String>,
+    /// The size in bytes under which response bodies are sent uncompressed.
+    pub min_size: usize,
+}
+
+impl Compression {
+    pub(crate) const DEFAULT_ENCODINGS: [Encoding; 3] =
+        [Encoding::Zstd, Encoding::Brotli, Encoding::Gzip];
+    pub(crate) const DEFAULT_CONTENT_TYPES: [&str; 9] = [
+        "text/",
+        "application/json",
+        "application/javascript",
+        "application/xml",
+        "application/wasm",
+        "image/svg+xml",
+        "font/ttf",
+        "+json",
+        "+xml",
+    ];
+    pub(crate) const DEFAULT_MIN_SIZE: usize = 1024;
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression {
+            encodings: Compression::DEFAULT_ENCODINGS.to_vec(),
+            content_types: Compression::DEFAULT_CONTENT_TYPES
+                .iter()
+                .map(|content_type| content_type.to_string())
+                .collect(),
+            min_size: Compression::DEFAULT_MIN_SIZE,
+        }
+    }
+}
+
+/// Decoding of request bodies sent with a `Content-Encoding`, before they are dispatched to Swift.
+/// Bodies with an unsupported encoding are answered with 415 Unsupported Media Type.
+#[derive(Clone, Debug, Default)]
+pub struct Decompression {
+    /// The maximum size in bytes of a decoded body, larger ones being answered with 413 Payload Too Large.
+    /// Defaults to the body size limit of the route, as for bodies sent without encoding.
+    pub max_size: Option<u64>,
+}
+
+/// An in-memory cache of the GET responses of the routes opting in, whose hits are served without
+/// dispatching to Swift. Responses are stored as returned by their handler, according to their `Cache-Control`.
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    /// The maximum size in bytes of the cached responses, beyond which the least recently used ones are evicted.
+    pub max_size: usize,
+    /// How long responses without `max-age` or `s-maxage` are cached, or `None` to not cache them.
+    pub default_ttl: Option<Duration>,
+    /// The longest a response is cached, whatever its `Cache-Control`.
+    pub max_ttl: Duration,
+    /// The request headers whose values are part of the cache key, e.g. `Accept-Language`.
+    /// Responses whose `Vary` header lists other ones are not cached.
+    pub vary_headers: Vec<HeaderName>,
+}
+
+impl ResponseCache {
+    pub(crate) const DEFAULT_MAX_SIZE: usize = 64 * 1024 * 1024;
+    pub(crate) const DEFAULT_MAX_TTL: Duration = Duration::from_secs(60 * 60);
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        ResponseCache {
+            max_size: ResponseCache::DEFAULT_MAX_SIZE,
+            default_ttl: None,
+            max_ttl: ResponseCache::DEFAULT_MAX_TTL,
+            vary_headers: Vec::new(),
+        }
+    }
+}
+
+/// How the requests of a server are identified, to correlate their handling across Rust and Swift.
+#[derive(Clone, Debug)]
+pub struct RequestIds {
+    /// The header echoing the id of a request in its response, and carrying a trusted incoming one.
+    pub header: HeaderName,
+    /// Whether the id sent by the client (or a proxy in front of the server) in `header` is used,
+    /// when well-formed, instead of generating one.
+    pub trust_incoming: bool,
+}
+
+impl Default for RequestIds {
+    fn default() -> Self {
+        RequestIds {
+            header: HeaderName::from_static("x-request-id"),
+            trust_incoming: false,
+        }
+    }
+}
+
+/// Configuration of a server, assembled by the client before starting it.
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub listeners: Vec<ListenerConfig>,
+    /// Whether HTTP/2 is served, negotiated through ALPN on TLS listeners,
+    /// and detected from the connection preface (h2c prior knowledge) on cleartext ones.
+    pub http2: bool,
+    /// How the Tokio runtime serving the listeners is built.
+    pub runtime: RuntimeConfig,
+    pub limits: RequestLimits,
+    pub connections: ConnectionLimits,
+    pub dispatch: DispatchLimits,
+    pub load_shedding: LoadShedding,
+    /// The rate limit of every route without its own, enforced before dispatching to Swift.
+    pub rate_limit: Option<RateLimit>,
+    /// The proxies whose `Forwarded` and `X-Forwarded-*` headers are trusted to describe the client.
+    pub trusted_proxies: Vec<Cidr>,
+    /// Response compression, disabled when `None`.
+    pub compression: Option<Compression>,
+    /// Request body decompression, disabled when `None`: encoded bodies are then dispatched as they are.
+    pub decompression: Option<Decompression>,
+    /// The response cache of the routes opting in, disabled when `None`.
+    pub cache: Option<ResponseCache>,
+    /// The CORS policy of every route without its own, disabled when `None`.
+    pub cors: Option<Cors>,
+    pub request_ids: RequestIds,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listeners: Vec::new(),
+            http2: true,
+            runtime: RuntimeConfig::default(),
+            limits: RequestLimits::default(),
+            connections: ConnectionLimits::default(),
+            dispatch: DispatchLimits::default(),
+            load_shedding: LoadShedding::default(),
+            rate_limit: None,
+            trusted_proxies: Vec::new(),
+            compression: None,
+            decompression: None,
+            cache: None,
+            cors: None,
+            request_ids: RequestIds::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Returns a configuration with a single listener on `port`,
+    /// bound to `KIRI_BIND_HOST` if set, or to the IPv4 loopback otherwise.
+    pub fn with_port(port: Port) -> ServerConfig {
+        ServerConfig {
+            listeners: vec![ListenerConfig::tcp(SocketAddr::new(default_host(), port))],
+            ..ServerConfig::default()
+        }
+    }
+
+    /// Returns the configuration of the servers started without one, by `kiri_server_start` and
+    /// `kiri_server_start_with_router`: `with_port` without the limits added after those functions,
+    /// so that their embedders keep the behavior they were written against.
+    pub fn unconfigured(port: Port) -> ServerConfig {
+        let mut config = ServerConfig::with_port(port);
+        config.limits.max_body_size = u64::MAX;
+        config.limits.body_read_timeout = None;
+        config.connections.header_read_timeout = None;
+        config.connections.idle_timeout = None;
+        return config;
+    }
+}
+
+/// The host used by listeners that do not specify one.
+pub fn default_host() -> IpAddr {
+    std::env::var("KIRI_BIND_HOST")
+        .ok()
+        .and_then(|value| value.parse::<IpAddr>().ok())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
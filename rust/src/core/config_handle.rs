@@ -0,0 +1,24 @@
+use std::sync::Mutex;
+
+use crate::core::{config::ServerConfig, server::ListenerError};
+
+pub struct ServerConfigHandle {
+    pub config: Mutex<ServerConfig>,
+    /// The listeners that failed to bind during the last start attempt with this configuration.
+    pub listener_errors: Mutex<Vec<ListenerError>>,
+}
+
+impl ServerConfigHandle {
+    pub fn new() -> ServerConfigHandle {
+        ServerConfigHandle {
+            config: Mutex::new(ServerConfig::default()),
+            listener_errors: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for ServerConfigHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{Notify, OwnedSemaphorePermit},
+};
+
+use crate::core::{
+    config::{ConnectionLimits, ListenerAddress},
+    stats::ServerStats,
+    stream::Stream,
+};
+
+/// What the server knows about the connection a request was received on.
+#[derive(Debug)]
+pub struct ConnectionInfo {
+    /// Identifies the connection among the ones accepted by the server, starting at 1.
+    pub id: u64,
+    /// The address of the client, as decoded from the PROXY header on listeners that expect one.
+    /// Unknown on Unix domain sockets.
+    pub remote_address: Option<SocketAddr>,
+    /// The address the client connected to: the listener's, or the IP that received the connection.
+    pub local_address: ListenerAddress,
+    /// Whether the connection is TLS.
+    pub tls: bool,
+}
+
+/// Why a connection is closed by the server before the client closes it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expiry {
+    /// The client started a request but did not finish sending its head in time.
+    HeaderReadTimeout,
+    /// No request was in flight for longer than the idle timeout.
+    Idle,
+    /// The connection served its maximum number of requests.
+    MaxRequests,
+}
+
+/// Counts an accepted connection as open, and holds its slot of `ConnectionLimits::max_connections`, until dropped.
+pub struct OpenConnection {
+    _slot: Option<OwnedSemaphorePermit>,
+    stats: Arc<ServerStats>,
+}
+
+impl OpenConnection {
+    pub fn new(slot: Option<OwnedSemaphorePermit>, stats: Arc<ServerStats>) -> OpenConnection {
+        stats.open_connections.fetch_add(1, Ordering::Relaxed);
+        OpenConnection { _slot: slot, stats }
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.stats.open_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The activity of a single connection, observed by its stream and service to enforce the `ConnectionLimits`.
+/// Times are stored as milliseconds since the connection was opened, plus one so that zero means unset.
+pub struct ConnectionActivity {
+    opened: Instant,
+    in_flight: AtomicUsize,
+    served: AtomicU64,
+    /// When the first byte of a request head was read, while no request was in flight.
+    head_started: AtomicU64,
+    /// When the last in-flight request finished.
+    idle_since: AtomicU64,
+    /// HTTP/2 multiplexes requests with control frames, so its reads don't start request heads.
+    http2: AtomicBool,
+    changed: Notify,
+}
+
+impl ConnectionActivity {
+    pub fn new(http2: bool) -> ConnectionActivity {
+        ConnectionActivity {
+            opened: Instant::now(),
+            in_flight: AtomicUsize::new(0),
+            served: AtomicU64::new(0),
+            head_started: AtomicU64::new(0),
+            idle_since: AtomicU64::new(1),
+            http2: AtomicBool::new(http2),
+            changed: Notify::new(),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        return self.opened.elapsed().as_millis() as u64 + 1;
+    }
+
+    /// Records that bytes were read from the client.
+    fn read(&self) {
+        if self.http2.load(Ordering::Relaxed) || self.in_flight.load(Ordering::Acquire) > 0 {
+            return;
+        }
+
+        let started =
+            self.head_started
+                .compare_exchange(0, self.now(), Ordering::AcqRel, Ordering::Relaxed);
+        if started.is_ok() {
+            self.changed.notify_one();
+        }
+    }
+
+    /// Whether any request was received on the connection.
+    pub fn served_any(&self) -> bool {
+        return self.served.load(Ordering::Relaxed) > 0;
+    }
+
+    /// Records that hyper parsed the head of a request, until the returned guard is dropped.
+    pub fn begin_request(self: &Arc<Self>, http2: bool) -> RequestGuard {
+        if http2 {
+            self.http2.store(true, Ordering::Relaxed);
+        }
+
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        self.served.fetch_add(1, Ordering::Relaxed);
+        self.head_started.store(0, Ordering::Release);
+        self.changed.notify_one();
+
+        return RequestGuard {
+            activity: self.clone(),
+        };
+    }
+
+    /// Resolves once the connection exceeds one of the `limits`.
+    pub async fn expired(&self, limits: &ConnectionLimits) -> Expiry {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if let Some(max_requests) = limits.max_requests
+                && self.served.load(Ordering::Relaxed) >= max_requests
+            {
+                return Expiry::MaxRequests;
+            }
+
+            let now = self.now();
+            let mut deadline: Option<u64> = None;
+
+            let head_started = self.head_started.load(Ordering::Acquire);
+            if let Some(timeout) = limits.header_read_timeout
+                && head_started != 0
+            {
+                let expires = head_started.saturating_add(millis(timeout));
+                if now >= expires {
+                    return Expiry::HeaderReadTimeout;
+                }
+                deadline = Some(expires);
+            }
+
+            if let Some(timeout) = limits.idle_timeout
+                && head_started == 0
+                && self.in_flight.load(Ordering::Acquire) == 0
+            {
+                let expires = self
+                    .idle_since
+                    .load(Ordering::Acquire)
+                    .saturating_add(millis(timeout));
+                if now >= expires {
+                    return Expiry::Idle;
+                }
+                deadline = Some(deadline.map_or(expires, |d| d.min(expires)));
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    let sleep = tokio::time::sleep(Duration::from_millis(deadline - now));
+                    tokio::select! {
+                        _ = changed => {}
+                        _ = sleep => {}
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+}
+
+/// Marks a request as in flight on its connection while alive.
+pub struct RequestGuard {
+    activity: Arc<ConnectionActivity>,
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        let activity = &self.activity;
+        if activity.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            activity.idle_since.store(activity.now(), Ordering::Release);
+        }
+        activity.changed.notify_one();
+    }
+}
+
+/// A stream that reports the reads of the client to the activity of its connection.
+pub struct TrackedStream {
+    inner: Stream,
+    activity: Arc<ConnectionActivity>,
+}
+
+impl TrackedStream {
+    pub fn new(inner: Stream, activity: Arc<ConnectionActivity>) -> TrackedStream {
+        TrackedStream { inner, activity }
+    }
+}
+
+impl AsyncRead for TrackedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if buf.filled().len() > filled {
+            this.activity.read();
+        }
+        return result;
+    }
+}
+
+impl AsyncWrite for TrackedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// The milliseconds of a timeout set over FFI to any value, saturating instead of wrapping around.
+fn millis(timeout: Duration) -> u64 {
+    return u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+}
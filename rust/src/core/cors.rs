@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use hyper::{
+    Body, Method, Request, Response, StatusCode,
+    header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+        ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+        ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, HeaderMap, HeaderValue,
+        ORIGIN, VARY,
+    },
+};
+
+/// A cross-origin resource sharing policy: which origins may read the responses of a route from a browser,
+/// and with which methods, headers, and credentials.
+#[derive(Clone, Debug)]
+pub struct Cors {
+    /// The allowed origins, lowercased: `*` for any, an exact origin like `https://example.com`,
+    /// or a pattern where `*` matches any part of the origin, like `https://*.example.com`.
+    pub origins: Vec<String>,
+    pub methods: Vec<Method>,
+    /// The request headers allowed in requests, lowercased, where `*` allows any.
+    pub headers: Vec<String>,
+    /// Whether browsers may send cookies and authorization with requests, and expose the responses.
+    /// Origins matching `*` are then echoed, as browsers reject a wildcard with credentials.
+    pub credentials: bool,
+    /// How long browsers may cache the result of a preflight request.
+    pub max_age: Option<Duration>,
+}
+
+impl Cors {
+    pub const DEFAULT_METHODS: [Method; 3] = [Method::GET, Method::HEAD, Method::POST];
+
+    /// Whether `request` is a preflight request, sent by browsers before a request they need permission for.
+    pub fn is_preflight(request: &Request<Body>) -> bool {
+        let headers = request.headers();
+        return request.method() == Method::OPTIONS
+            && headers.contains_key(ORIGIN)
+            && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+    }
+
+    /// The `Access-Control-Allow-Origin` of a request from `origin`, or `None` when it is not allowed.
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        let origin_str = origin.to_str().ok()?.to_ascii_lowercase();
+
+        if self.origins.iter().any(|allowed| allowed == "*") {
+            return match self.credentials {
+                true => Some(origin.clone()),
+                false => Some(HeaderValue::from_static("*")),
+            };
+        }
+
+        return self
+            .origins
+            .iter()
+            .any(|allowed| matches_pattern(allowed, &origin_str))
+            .then(|| origin.clone());
+    }
+
+    /// Answers a preflight request with 204 No Content and the permissions it asks for,
+    /// or with 403 Forbidden when its origin, method, or headers are not allowed.
+    pub fn preflight(&self, request: &Request<Body>) -> Response<Body> {
+        let headers = request.headers();
+        let mut response = Response::new(Body::empty());
+        vary(
+            response.headers_mut(),
+            "origin, access-control-request-method, access-control-request-headers",
+        );
+
+        let allow_origin = headers
+            .get(ORIGIN)
+            .and_then(|origin| self.allow_origin(origin));
+        let method_allowed = headers
+            .get(ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|value| Method::from_bytes(value.as_bytes()).ok())
+            .is_some_and(|method| self.methods.contains(&method));
+        let requested_headers: Vec<String> = headers
+            .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        let any_header = self.headers.iter().any(|allowed| allowed == "*");
+        let headers_allowed = any_header
+            || requested_headers
+                .iter()
+                .all(|name| self.headers.contains(name));
+
+        let Some(allow_origin) = allow_origin.filter(|_| method_allowed && headers_allowed) else {
+            *response.status_mut() = StatusCode::FORBIDDEN;
+            *response.body_mut() = Body::from("cors preflight rejected\n");
+            return response;
+        };
+
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        let response_headers = response.headers_mut();
+        response_headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.credentials {
+            response_headers.insert(
+                ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+
+        let methods = self
+            .methods
+            .iter()
+            .map(|method| method.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Ok(methods) = HeaderValue::from_str(&methods) {
+            response_headers.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+
+        // A wildcard is echoed as the requested headers, as browsers ignore it with credentials.
+        let allow_headers = match any_header {
+            true => requested_headers.join(", "),
+            false => self.headers.join(", "),
+        };
+        if !allow_headers.is_empty()
+            && let Ok(allow_headers) = HeaderValue::from_str(&allow_headers)
+        {
+            response_headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+        }
+
+        if let Some(max_age) = self.max_age {
+            response_headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+
+        return response;
+    }
+
+    /// Lets the browser of a request from an allowed `origin` read `response`.
+    pub fn apply(&self, origin: &HeaderValue, response: &mut Response<Body>) {
+        let allow_origin = self.allow_origin(origin);
+        let headers = response.headers_mut();
+
+        // Unless any origin gets the same wildcard, the response differs from one origin to another.
+        if allow_origin
+            .as_ref()
+            .is_none_or(|allow_origin| allow_origin != "*")
+        {
+            vary(headers, "origin");
+        }
+
+        let Some(allow_origin) = allow_origin else {
+            return;
+        };
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.credentials {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+}
+
+/// Whether `origin` matches `pattern`, where each `*` matches any sequence of characters.
+fn matches_pattern(pattern: &str, origin: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = origin.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: the origin must be the pattern itself.
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    return rest.ends_with(last);
+}
+
+/// Appends `names` to the `Vary` header, unless it already varies on everything.
+fn vary(headers: &mut HeaderMap, names: &'static str) {
+    let varies_on_everything = headers
+        .get_all(VARY)
+        .iter()
+        .any(|value| value.as_bytes().trim_ascii() == b"*");
+    if !varies_on_everything {
+        headers.append(VARY, HeaderValue::from_static(names));
+    }
+}
@@ -0,0 +1,86 @@
+use hyper::{
+    Body, Request, Response, StatusCode,
+    header::{CONTENT_LENGTH, ETAG, HeaderValue, IF_NONE_MATCH},
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A strong entity tag derived from the bytes of a response body. Its hash, 64-bit FNV-1a, is fully specified,
+/// so that the same body gets the same tag from every build and every instance behind a load balancer.
+pub fn entity_tag(body: &[u8]) -> String {
+    let hash = body.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    return format!("\"{:x}-{:016x}\"", body.len(), hash);
+}
+
+/// Whether `If-None-Match` lists `etag`, by weak comparison: the `W/` prefix is ignored on both sides.
+pub fn none_match(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    return if_none_match.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    });
+}
+
+/// The `If-None-Match` header of a GET request, kept once its body is consumed.
+pub struct ConditionalHeaders {
+    if_none_match: Option<String>,
+}
+
+impl ConditionalHeaders {
+    pub fn from_request(request: &Request<Body>) -> ConditionalHeaders {
+        let if_none_match = match request.method() == hyper::Method::GET {
+            true => request
+                .headers()
+                .get(IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string()),
+            false => None,
+        };
+
+        return ConditionalHeaders { if_none_match };
+    }
+
+    /// Tags a 200 response that has no `ETag` with the hash of its `body`, and turns it into
+    /// 304 Not Modified when the client already holds it. Returns the body to send.
+    pub fn apply(&self, response: &mut Response<Body>, body: Vec<u8>) -> Vec<u8> {
+        if response.status() != StatusCode::OK {
+            return body;
+        }
+
+        let headers = response.headers_mut();
+        let etag = match headers.get(ETAG).and_then(|value| value.to_str().ok()) {
+            Some(etag) => etag.to_string(),
+            None => {
+                let etag = entity_tag(&body);
+                if let Ok(value) = HeaderValue::from_str(&etag) {
+                    headers.insert(ETAG, value);
+                }
+                etag
+            }
+        };
+
+        if let Some(if_none_match) = &self.if_none_match
+            && none_match(if_none_match, &etag)
+        {
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            response.headers_mut().remove(CONTENT_LENGTH);
+            return Vec::new();
+        }
+
+        return body;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_tags_are_stable() {
+        assert_eq!(entity_tag(b""), "\"0-cbf29ce484222325\"");
+        assert_eq!(entity_tag(b"hello world"), "\"b-779a65e7023cd2e7\"");
+    }
+}
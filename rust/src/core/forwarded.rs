@@ -0,0 +1,420 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use hyper::{Body, Request, header::HeaderName};
+
+use crate::core::connection::ConnectionInfo;
+
+const FORWARDED: HeaderName = HeaderName::from_static("forwarded");
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+/// A range of IP addresses, e.g. `10.0.0.0/8` or `2001:db8::/32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, address: IpAddr) -> bool {
+        // Addresses are compared canonical, IPv4 ones as mapped when the range is IPv6.
+        let address = match (self.network, address.to_canonical()) {
+            (IpAddr::V6(_), IpAddr::V4(address)) => IpAddr::V6(address.to_ipv6_mapped()),
+            (_, address) => address,
+        };
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                return u32::from(network) & mask == u32::from(address) & mask;
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                return u128::from(network) & mask == u128::from(address) & mask;
+            }
+            _ => return false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    /// Parses `address/prefix_len`, or a single address. IPv4-mapped IPv6 ranges within `::ffff:0:0/96`
+    /// are kept as IPv4 ones.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match value.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (value, None),
+        };
+
+        let network = IpAddr::from_str(address.trim())
+            .map_err(|e| format!("invalid address {:?}: {}", address, e))?;
+        let max_prefix_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix_len)
+                .ok_or_else(|| format!("invalid prefix length {:?}", prefix_len))?,
+            None => max_prefix_len,
+        };
+
+        if let IpAddr::V6(v6) = network
+            && let Some(v4) = v6.to_ipv4_mapped()
+            && prefix_len >= 96
+        {
+            return Ok(Cidr {
+                network: IpAddr::V4(v4),
+                prefix_len: prefix_len - 96,
+            });
+        }
+        return Ok(Cidr {
+            network,
+            prefix_len,
+        });
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// The client of a request as seen through the trusted proxies in front of the server.
+pub struct ClientInfo {
+    /// The address of the client, with port 0 when the proxies did not forward it.
+    pub address: Option<SocketAddr>,
+    /// `http` or `https`.
+    pub scheme: String,
+    /// The host requested by the client, from the `Host` header or the HTTP/2 authority.
+    pub host: Option<String>,
+}
+
+/// A hop of the chain of proxies, as described by a forwarding header.
+#[derive(Default)]
+struct Hop {
+    address: Option<SocketAddr>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+/// Resolves the client of `request`. The forwarding headers are only read when the peer is one of
+/// `trusted_proxies`, walking the chain of proxies from the nearest one until an untrusted address.
+pub fn resolve(
+    request: &Request<Body>,
+    connection: &ConnectionInfo,
+    trusted_proxies: &[Cidr],
+) -> ClientInfo {
+    let mut client = ClientInfo {
+        address: connection.remote_address,
+        scheme: if connection.tls { "https" } else { "http" }.to_string(),
+        host: request_host(request),
+    };
+
+    let is_trusted = |address: &SocketAddr| {
+        trusted_proxies
+            .iter()
+            .any(|cidr| cidr.contains(address.ip()))
+    };
+
+    let Some(peer) = connection.remote_address else {
+        return client;
+    };
+    if !is_trusted(&peer) {
+        return client;
+    }
+
+    // `Forwarded` supersedes the `X-Forwarded-*` headers when a proxy sends it.
+    let hops = match request.headers().contains_key(FORWARDED) {
+        true => forwarded_hops(request),
+        false => {
+            // The nearest proxy's values apply to the client, as the addresses in between are not described.
+            if let Some(proto) = header_list(request, &X_FORWARDED_PROTO).last() {
+                client.scheme = scheme(proto).unwrap_or(client.scheme);
+            }
+            if let Some(host) = header_list(request, &X_FORWARDED_HOST).last() {
+                client.host = Some(host.to_string());
+            }
+            x_forwarded_hops(request)
+        }
+    };
+
+    // Walks from the nearest proxy towards the client, each hop being vouched for by the trusted one after it.
+    for hop in hops.iter().rev() {
+        if let Some(proto) = hop.proto.as_deref().and_then(scheme) {
+            client.scheme = proto;
+        }
+        if let Some(host) = &hop.host {
+            client.host = Some(host.clone());
+        }
+
+        match hop.address {
+            Some(address) => {
+                client.address = Some(address);
+                if !is_trusted(&address) {
+                    break;
+                }
+            }
+            // An obfuscated or unknown address ends the chain, keeping the last known hop as the client.
+            None => break,
+        }
+    }
+
+    return client;
+}
+
+fn request_host(request: &Request<Body>) -> Option<String> {
+    if let Some(authority) = request.uri().authority() {
+        return Some(authority.to_string());
+    }
+
+    return request
+        .headers()
+        .get(hyper::header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .map(|host| host.to_string());
+}
+
+/// Splits the values of every `name` header on commas.
+fn header_list<'a>(request: &'a Request<Body>, name: &HeaderName) -> Vec<&'a str> {
+    return request
+        .headers()
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect();
+}
+
+/// Parses `Forwarded: for=192.0.2.60;proto=https;host=example.com, for="[2001:db8::1]:4711"` (RFC 7239).
+fn forwarded_hops(request: &Request<Body>) -> Vec<Hop> {
+    return header_list(request, &FORWARDED)
+        .into_iter()
+        .map(|element| {
+            let mut hop = Hop::default();
+            for pair in element.split(';') {
+                let Some((name, value)) = pair.split_once('=') else {
+                    continue;
+                };
+
+                let value = value.trim().trim_matches('"');
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "for" => hop.address = parse_node(value),
+                    "proto" => hop.proto = Some(value.to_string()),
+                    "host" => hop.host = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+            hop
+        })
+        .collect();
+}
+
+/// Parses `X-Forwarded-For: 203.0.113.7, 10.0.0.2`.
+fn x_forwarded_hops(request: &Request<Body>) -> Vec<Hop> {
+    return header_list(request, &X_FORWARDED_FOR)
+        .into_iter()
+        .map(|address| Hop {
+            address: parse_node(address),
+            ..Hop::default()
+        })
+        .collect();
+}
+
+/// Accepts the `http` and `https` protocols only, in lowercase.
+fn scheme(proto: &str) -> Option<String> {
+    let proto = proto.to_ascii_lowercase();
+    return matches!(proto.as_str(), "http" | "https").then_some(proto);
+}
+
+/// Parses an address as `192.0.2.60`, `192.0.2.60:4711`, `2001:db8::1` or `[2001:db8::1]:4711`.
+/// Obfuscated identifiers and `unknown` are not addresses.
+fn parse_node(value: &str) -> Option<SocketAddr> {
+    if let Ok(address) = SocketAddr::from_str(value) {
+        return Some(address);
+    }
+
+    let address = value.trim_start_matches('[').trim_end_matches(']');
+    return IpAddr::from_str(address)
+        .ok()
+        .map(|address| SocketAddr::new(address, 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use hyper::{Body, Request};
+
+    use super::{Cidr, ClientInfo, resolve};
+    use crate::core::{config::ListenerAddress, connection::ConnectionInfo};
+
+    fn cidrs(values: &[&str]) -> Vec<Cidr> {
+        return values
+            .iter()
+            .map(|value| value.parse().expect("CIDR"))
+            .collect();
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        return text.parse().expect("address");
+    }
+
+    fn socket(text: &str) -> Option<SocketAddr> {
+        return Some(text.parse().expect("socket address"));
+    }
+
+    fn client_of(peer: &str, headers: &[(&str, &str)], trusted_proxies: &[&str]) -> ClientInfo {
+        let mut request = Request::get("/").header("host", "origin.example");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(Body::empty()).expect("request");
+        let local: SocketAddr = "127.0.0.1:8080".parse().expect("address");
+        let connection = ConnectionInfo {
+            id: 1,
+            remote_address: Some(peer.parse().expect("peer")),
+            local_address: ListenerAddress::Tcp(local),
+            tls: false,
+        };
+        return resolve(&request, &connection, &cidrs(trusted_proxies));
+    }
+
+    #[test]
+    fn cidrs_contain_the_addresses_of_their_range() {
+        let private = "10.0.0.0/8".parse::<Cidr>().expect("CIDR");
+        assert!(private.contains(ip("10.1.2.3")));
+        assert!(private.contains(ip("::ffff:10.1.2.3")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(!private.contains(ip("2001:db8::1")));
+
+        let documentation = "2001:db8::/32".parse::<Cidr>().expect("CIDR");
+        assert!(documentation.contains(ip("2001:db8:1::1")));
+        assert!(!documentation.contains(ip("2001:db9::1")));
+        assert!(!documentation.contains(ip("10.0.0.1")));
+
+        let single = "192.0.2.1".parse::<Cidr>().expect("CIDR");
+        assert!(single.contains(ip("192.0.2.1")));
+        assert!(!single.contains(ip("192.0.2.2")));
+        assert!(
+            "0.0.0.0/0"
+                .parse::<Cidr>()
+                .expect("CIDR")
+                .contains(ip("8.8.8.8"))
+        );
+    }
+
+    #[test]
+    fn ipv4_mapped_cidrs_use_the_prefix_length_of_ipv6() {
+        let mapped = "::ffff:10.0.0.0/104".parse::<Cidr>().expect("CIDR");
+        assert_eq!(mapped, "10.0.0.0/8".parse().expect("CIDR"));
+        assert!(mapped.contains(ip("10.1.2.3")));
+        assert!(mapped.contains(ip("::ffff:10.1.2.3")));
+        assert!(!mapped.contains(ip("11.0.0.1")));
+
+        let all_mapped = "::ffff:0:0/96".parse::<Cidr>().expect("CIDR");
+        assert!(all_mapped.contains(ip("8.8.8.8")));
+
+        // Shorter prefixes cover more than IPv4-mapped addresses, and stay IPv6.
+        let wider = "::ffff:0:0/80".parse::<Cidr>().expect("CIDR");
+        assert!(wider.contains(ip("8.8.8.8")));
+        assert!(wider.contains(ip("::1:2:3")));
+    }
+
+    #[test]
+    fn invalid_cidrs_are_rejected() {
+        for value in [
+            "10.0.0.0/33",
+            "2001:db8::/129",
+            "::ffff:10.0.0.0/129",
+            "10.0.0.0/",
+            "10.0.0.0/-1",
+            "10.0.0/8",
+            "example.com",
+            "",
+        ] {
+            assert!(value.parse::<Cidr>().is_err(), "{:?} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn forwarding_headers_are_only_read_from_trusted_peers() {
+        let headers = [
+            ("x-forwarded-for", "203.0.113.7"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "public.example"),
+        ];
+
+        let client = client_of("198.51.100.1:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("198.51.100.1:5000"));
+        assert_eq!(client.scheme, "http");
+        assert_eq!(client.host.as_deref(), Some("origin.example"));
+
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("203.0.113.7:0"));
+        assert_eq!(client.scheme, "https");
+        assert_eq!(client.host.as_deref(), Some("public.example"));
+
+        // An IPv4 peer accepted on a dual-stack socket is trusted as its IPv4 address.
+        let client = client_of("[::ffff:10.0.0.2]:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("203.0.113.7:0"));
+    }
+
+    #[test]
+    fn x_forwarded_for_is_walked_past_trusted_hops_only() {
+        let headers = [(
+            "x-forwarded-for",
+            "192.0.2.66, 203.0.113.7, 10.0.0.3, 10.0.0.4",
+        )];
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("203.0.113.7:0"));
+
+        // A client cannot get past the proxies by prepending addresses.
+        let headers = [("x-forwarded-for", "10.0.0.9, 203.0.113.7")];
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("203.0.113.7:0"));
+
+        // Unparsable entries end the chain at the last known hop.
+        let headers = [("x-forwarded-for", "203.0.113.7, garbage, 10.0.0.3")];
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("10.0.0.3:0"));
+    }
+
+    #[test]
+    fn forwarded_headers_carry_quoted_ipv6_and_ports() {
+        let headers = [(
+            "forwarded",
+            "for=\"[2001:db8::1]:4711\";proto=https;host=public.example, for=10.0.0.3:8000",
+        )];
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.address, socket("[2001:db8::1]:4711"));
+        assert_eq!(client.scheme, "https");
+        assert_eq!(client.host.as_deref(), Some("public.example"));
+
+        // `Forwarded` supersedes `X-Forwarded-For`, and obfuscated identifiers are not addresses.
+        let headers = [
+            ("forwarded", "for=_hidden, for=\"[2001:db8::2]\""),
+            ("x-forwarded-for", "203.0.113.7"),
+        ];
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8", "2001:db8::/32"]);
+        assert_eq!(client.address, socket("[2001:db8::2]:0"));
+
+        // Unsupported protocols keep the scheme of the connection.
+        let headers = [("forwarded", "for=203.0.113.7;proto=gopher")];
+        let client = client_of("10.0.0.2:5000", &headers, &["10.0.0.0/8"]);
+        assert_eq!(client.scheme, "http");
+    }
+}
@@ -1,33 +1,153 @@
-use crate::core::types::StatusCode;
+use std::net::SocketAddr;
+
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::core::{
+    config::ListenerAddress, connection::ConnectionInfo, forwarded::ClientInfo,
+    request_id::RequestId, types::StatusCode,
+};
+
+const ADDRESS_NONE: u8 = 0;
+const ADDRESS_IP: u8 = 1;
+const ADDRESS_UNIX: u8 = 2;
 
 /*
-* [u8  method]       -> 1
-* [u32 path_len]     -> 4
-* [bytes path UTF-8] -> bytes.len
-* [u32 body_len]     -> 4
-* [bytes body]       -> bytes.len
+* [u8  method]         -> 1
+* [u32 path_len]       -> 4
+* [bytes path UTF-8]   -> bytes.len
+* [u32 body_len]       -> 4
+* [bytes body]         -> bytes.len
+* [u8  version]        -> 1 (major * 10 + minor, e.g. 11 or 20)
+* [u64 connection_id]  -> 8
+* [u8  tls]            -> 1 (0 or 1)
+* [address remote]     -> see encode_address
+* [address local]      -> see encode_address
+* [address client]     -> see encode_address (resolved through trusted proxies)
+* [u16 scheme_len]     -> 2
+* [bytes scheme UTF-8] -> bytes.len
+* [u16 host_len]       -> 2 (0 when unknown)
+* [bytes host UTF-8]   -> bytes.len
+* [u16 request_id_len] -> 2
+* [bytes request_id]   -> bytes.len (visible ASCII)
 */
-pub fn encode_request(method: u8, path: &str, body: &[u8]) -> Vec<u8> {
+pub fn encode_request(
+    method: u8,
+    path: &str,
+    body: &[u8],
+    version: u8,
+    connection: &ConnectionInfo,
+    client: &ClientInfo,
+    request_id: &RequestId,
+) -> Vec<u8> {
     let path_bytes = path.as_bytes();
-    let mut out = Vec::with_capacity(1 + 4 + path_bytes.len() + 4 + body.len());
+    let mut out = Vec::with_capacity(1 + 4 + path_bytes.len() + 4 + body.len() + 1 + 9 + 3 * 64);
     out.push(method);
     out.extend_from_slice(&(path_bytes.len() as u32).to_le_bytes());
     out.extend_from_slice(path_bytes);
     out.extend_from_slice(&(body.len() as u32).to_le_bytes());
     out.extend_from_slice(body);
+    out.push(version);
+    out.extend_from_slice(&connection.id.to_le_bytes());
+    out.push(connection.tls as u8);
+    encode_socket_address(&mut out, connection.remote_address.as_ref());
+    encode_listener_address(&mut out, &connection.local_address);
+    encode_socket_address(&mut out, client.address.as_ref());
+    encode_text(&mut out, &client.scheme);
+    encode_text(&mut out, client.host.as_deref().unwrap_or_default());
+    encode_text(&mut out, &request_id.to_string());
     return out;
 }
 
-pub fn decode_response(bytes: &[u8]) -> Option<(StatusCode, Vec<u8>)> {
-    if bytes.len() < 6 {
-        return None;
+/*
+* [u8  kind]           -> 1 (0 none, 1 IP, 2 Unix domain socket)
+* [u16 text_len]       -> 2
+* [bytes text UTF-8]   -> bytes.len (the IP without brackets, or the socket path, `@` prefixed when abstract)
+* [u16 port]           -> 2 (0 unless IP)
+*/
+fn encode_address(out: &mut Vec<u8>, kind: u8, text: &str, port: u16) {
+    let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+    out.push(kind);
+    out.extend_from_slice(&(text.len() as u16).to_le_bytes());
+    out.extend_from_slice(text);
+    out.extend_from_slice(&port.to_le_bytes());
+}
+
+fn encode_text(out: &mut Vec<u8>, text: &str) {
+    let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(text.len() as u16).to_le_bytes());
+    out.extend_from_slice(text);
+}
+
+fn encode_socket_address(out: &mut Vec<u8>, address: Option<&SocketAddr>) {
+    match address {
+        Some(address) => encode_address(
+            out,
+            ADDRESS_IP,
+            &address.ip().to_canonical().to_string(),
+            address.port(),
+        ),
+        None => encode_address(out, ADDRESS_NONE, "", 0),
+    }
+}
+
+fn encode_listener_address(out: &mut Vec<u8>, address: &ListenerAddress) {
+    match address {
+        ListenerAddress::Tcp(address) => encode_socket_address(out, Some(address)),
+        ListenerAddress::Unix(path) => {
+            encode_address(out, ADDRESS_UNIX, &path.to_string_lossy(), 0)
+        }
+        ListenerAddress::UnixAbstract(name) => encode_address(
+            out,
+            ADDRESS_UNIX,
+            &format!("@{}", String::from_utf8_lossy(name)),
+            0,
+        ),
     }
+}
+
+/*
+* [u16 status]         -> 2
+* [u32 body_len]       -> 4
+* [bytes body]         -> bytes.len
+* [u16 header_count]   -> 2 (the header block may be omitted when empty)
+* per header:
+*   [u16 name_len]     -> 2
+*   [bytes name]       -> bytes.len
+*   [u32 value_len]    -> 4
+*   [bytes value]      -> bytes.len
+*/
+pub fn decode_response(bytes: &[u8]) -> Option<(StatusCode, HeaderMap, Vec<u8>)> {
+    let mut reader = Reader { bytes, offset: 0 };
+
+    let status = StatusCode::from_le_bytes(reader.take(2)?.try_into().ok()?);
+    let body_len = u32::from_le_bytes(reader.take(4)?.try_into().ok()?) as usize;
+    let body = reader.take(body_len)?.to_vec();
 
-    let status = StatusCode::from_le_bytes([bytes[0], bytes[1]]);
-    let body_len = u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]) as usize;
-    if bytes.len() < 6 + body_len {
-        return None;
+    let mut headers = HeaderMap::new();
+    if reader.offset < bytes.len() {
+        let count = u16::from_le_bytes(reader.take(2)?.try_into().ok()?);
+        for _ in 0..count {
+            let name_len = u16::from_le_bytes(reader.take(2)?.try_into().ok()?) as usize;
+            let name = HeaderName::from_bytes(reader.take(name_len)?).ok()?;
+            let value_len = u32::from_le_bytes(reader.take(4)?.try_into().ok()?) as usize;
+            let value = HeaderValue::from_bytes(reader.take(value_len)?).ok()?;
+            headers.append(name, value);
+        }
     }
 
-    return Some((status, bytes[6..6 + body_len].to_vec()));
+    return Some((status, headers, body));
+}
+
+/// Reads consecutive fields of a frame, failing on truncation.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let field = self.bytes.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        return Some(field);
+    }
 }
@@ -0,0 +1,253 @@
+use std::{fmt, sync::Arc, time::Instant};
+
+use hyper::{
+    Body, Request, Response, StatusCode,
+    header::{HeaderName, HeaderValue},
+};
+
+use crate::core::{
+    forwarded::{Cidr, ClientInfo},
+    static_files::percent_decode,
+};
+
+const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// What layers know of the request they run around, besides the request itself.
+pub struct LayerContext<'a> {
+    pub client: &'a ClientInfo,
+    /// When the server started handling the request.
+    pub received: Instant,
+}
+
+/// A stage of the pipeline Rust runs around the handlers of the routes it is attached to,
+/// without crossing the FFI boundary.
+pub trait Layer: fmt::Debug + Send + Sync {
+    /// Runs before the request is rate limited, read and dispatched. Returning a response answers the request
+    /// without dispatching it, the layers before this one still running `after` on it.
+    fn before(&self, _request: &Request<Body>, _context: &LayerContext) -> Option<Response<Body>> {
+        return None;
+    }
+
+    /// Runs on the response of the request, whether from its handler, the cache, or Rust itself.
+    fn after(&self, _response: &mut Response<Body>, _context: &LayerContext) {}
+}
+
+/// Builds the built-in layer `name` from its `config`: lines of `key=value`, whose keys depend on the layer.
+///
+/// - `response_headers`: each line is a header set on responses that do not have it, e.g. `x-frame-options=DENY`.
+/// - `ip_filter`: `allow` and `deny` list client addresses or CIDR ranges, comma-separated. Clients outside of
+///   the allowed ones when any, or within the denied ones, are answered with 403 Forbidden.
+/// - `api_key`: requests whose `header` (`x-api-key` by default) is not one of the comma-separated `keys`
+///   are answered with 401 Unauthorized.
+/// - `server_timing`: no keys. Adds the time spent handling the request to a `Server-Timing` header.
+pub fn build(name: &str, config: &str) -> Result<Arc<dyn Layer>, String> {
+    let entries = parse_config(config)?;
+
+    return match name {
+        "response_headers" => Ok(Arc::new(ResponseHeaders::new(&entries)?)),
+        "ip_filter" => Ok(Arc::new(IpFilter::new(&entries)?)),
+        "api_key" => Ok(Arc::new(ApiKey::new(&entries)?)),
+        "server_timing" => match entries.first() {
+            Some((key, _)) => Err(format!("unknown server_timing key {:?}", key)),
+            None => Ok(Arc::new(ServerTiming)),
+        },
+        _ => Err(format!("unknown layer {:?}", name)),
+    };
+}
+
+/// Runs `before` of each layer in order, until one answers the request.
+/// Returns its response, with the number of layers whose `after` applies to it.
+pub fn run_before(
+    layers: &[Arc<dyn Layer>],
+    request: &Request<Body>,
+    context: &LayerContext,
+) -> Option<(Response<Body>, usize)> {
+    return layers
+        .iter()
+        .enumerate()
+        .find_map(|(index, layer)| Some((layer.before(request, context)?, index)));
+}
+
+/// Runs `after` of each layer in reverse order, so that the first layer sees the response last.
+pub fn run_after(layers: &[Arc<dyn Layer>], response: &mut Response<Body>, context: &LayerContext) {
+    for layer in layers.iter().rev() {
+        layer.after(response, context);
+    }
+}
+
+/// Whether the request `path` lies under `prefix`, segment-wise, where a `:param` segment of the prefix
+/// matches any segment. Segments of the path are compared percent-decoded, as static mounts serve them.
+pub fn is_under_prefix(path: &str, prefix: &str) -> bool {
+    let mut path_segments = path.split('/').filter(|segment| !segment.is_empty());
+    return prefix
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .all(|prefix_segment| {
+            path_segments.next().is_some_and(|path_segment| {
+                prefix_segment.starts_with(':')
+                    || percent_decode(path_segment)
+                        .is_some_and(|path_segment| path_segment == prefix_segment)
+            })
+        });
+}
+
+fn parse_config(config: &str) -> Result<Vec<(String, String)>, String> {
+    return config
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+            None => Err(format!("expected key=value, found {:?}", line)),
+        })
+        .collect();
+}
+
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    return value
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty());
+}
+
+fn plain_response(status: StatusCode, body: &'static str) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    return response;
+}
+
+#[derive(Debug)]
+struct ResponseHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl ResponseHeaders {
+    fn new(entries: &[(String, String)]) -> Result<ResponseHeaders, String> {
+        let headers = entries
+            .iter()
+            .map(|(name, value)| {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| format!("invalid header name {:?}: {}", name, e))?;
+                let value = HeaderValue::from_str(value)
+                    .map_err(|e| format!("invalid value of {}: {}", name, e))?;
+                Ok((name, value))
+            })
+            .collect::<Result<_, String>>()?;
+
+        return Ok(ResponseHeaders { headers });
+    }
+}
+
+impl Layer for ResponseHeaders {
+    fn after(&self, response: &mut Response<Body>, _context: &LayerContext) {
+        let headers = response.headers_mut();
+        for (name, value) in &self.headers {
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Clients of Unix domain sockets have no address, and are always let through.
+#[derive(Debug)]
+struct IpFilter {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl IpFilter {
+    fn new(entries: &[(String, String)]) -> Result<IpFilter, String> {
+        let mut filter = IpFilter {
+            allow: Vec::new(),
+            deny: Vec::new(),
+        };
+
+        for (key, value) in entries {
+            let ranges = match key.as_str() {
+                "allow" => &mut filter.allow,
+                "deny" => &mut filter.deny,
+                _ => return Err(format!("unknown ip_filter key {:?}", key)),
+            };
+            for range in list(value) {
+                ranges.push(range.parse()?);
+            }
+        }
+
+        return Ok(filter);
+    }
+}
+
+impl Layer for IpFilter {
+    fn before(&self, _request: &Request<Body>, context: &LayerContext) -> Option<Response<Body>> {
+        let address = context.client.address?.ip();
+        let denied = self.deny.iter().any(|range| range.contains(address));
+        let allowed =
+            self.allow.is_empty() || self.allow.iter().any(|range| range.contains(address));
+
+        return (denied || !allowed).then(|| plain_response(StatusCode::FORBIDDEN, "forbidden\n"));
+    }
+}
+
+#[derive(Debug)]
+struct ApiKey {
+    header: HeaderName,
+    keys: Vec<Vec<u8>>,
+}
+
+impl ApiKey {
+    fn new(entries: &[(String, String)]) -> Result<ApiKey, String> {
+        let mut header = HeaderName::from_static("x-api-key");
+        let mut keys = Vec::new();
+
+        for (key, value) in entries {
+            match key.as_str() {
+                "header" => {
+                    header = HeaderName::from_bytes(value.as_bytes())
+                        .map_err(|e| format!("invalid header name {:?}: {}", value, e))?;
+                }
+                "keys" => keys.extend(list(value).map(|key| key.as_bytes().to_vec())),
+                _ => return Err(format!("unknown api_key key {:?}", key)),
+            }
+        }
+
+        if keys.is_empty() {
+            return Err("api_key needs at least one key".to_string());
+        }
+
+        return Ok(ApiKey { header, keys });
+    }
+}
+
+impl Layer for ApiKey {
+    fn before(&self, request: &Request<Body>, _context: &LayerContext) -> Option<Response<Body>> {
+        let authorized = request.headers().get(&self.header).is_some_and(|value| {
+            self.keys
+                .iter()
+                .any(|key| constant_time_eq(key, value.as_bytes()))
+        });
+
+        return (!authorized).then(|| plain_response(StatusCode::UNAUTHORIZED, "unauthorized\n"));
+    }
+}
+
+/// Compares `a` and `b` in a time that does not depend on where they differ, so as not to leak keys.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    return a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0;
+}
+
+#[derive(Debug)]
+struct ServerTiming;
+
+impl Layer for ServerTiming {
+    fn after(&self, response: &mut Response<Body>, context: &LayerContext) {
+        let duration = context.received.elapsed().as_secs_f64() * 1_000.0;
+        if let Ok(value) = HeaderValue::from_str(&format!("app;dur={:.3}", duration)) {
+            response.headers_mut().append(SERVER_TIMING, value);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use std::{
+    fs, io,
+    net::SocketAddr,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use hyper::{Version, server::conn::Http, service::service_fn};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{
+    net::{TcpListener, UnixListener},
+    sync::{mpsc, watch},
+};
+
+#[cfg(feature = "tls")]
+use crate::core::tls::TlsState;
+use crate::{
+    core::{
+        config::{ListenerAddress, ListenerConfig},
+        connection::{ConnectionActivity, ConnectionInfo, Expiry, OpenConnection, TrackedStream},
+        proxy_protocol::{self, ProxyHeader},
+        server::{self, ServerContext},
+        stream::Stream,
+    },
+    error::KiriError,
+    status::KiriStatus,
+};
+
+const BACKLOG: i32 = 1024;
+/// How long a client has to complete the TLS handshake before the connection is dropped.
+#[cfg(feature = "tls")]
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Inner {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// A bound listener, ready to accept connections.
+pub struct Listener {
+    inner: Inner,
+    local_address: ListenerAddress,
+    /// The filesystem socket created by this listener, removed when the listener is dropped.
+    socket_path: Option<PathBuf>,
+    /// Whether connections start with a PROXY protocol header.
+    proxy_protocol: bool,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<TlsState>>,
+}
+
+impl Listener {
+    /// Binds the listener described by `config`, advertising HTTP/2 to TLS clients when `http2` is enabled.
+    /// Must be called from within the Tokio runtime that will accept its connections.
+    #[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+    pub fn bind(config: &ListenerConfig, http2: bool) -> Result<Listener, KiriError> {
+        // Load the certificates first, so that a TLS failure leaves no socket behind.
+        #[cfg(feature = "tls")]
+        let tls = match config.certificates.is_empty() {
+            true => None,
+            false => Some(Arc::new(TlsState::load(&config.certificates, http2)?)),
+        };
+
+        #[cfg(not(feature = "tls"))]
+        if !config.certificates.is_empty() {
+            return Err(KiriError::new(
+                KiriStatus::Unsupported,
+                "TLS requires building kiri_ffi with the `tls` feature",
+            ));
+        }
+
+        let listener = match &config.address {
+            ListenerAddress::Tcp(address) => bind_tcp(*address, config.ipv6_only),
+            ListenerAddress::Unix(path) => bind_unix(path, config),
+            ListenerAddress::UnixAbstract(name) => bind_unix_abstract(name),
+        };
+
+        let mut listener = listener.map_err(|e| {
+            KiriError::new(
+                KiriStatus::BindFailed,
+                format!("bind {} failed: {}", config.address, e),
+            )
+        })?;
+
+        listener.proxy_protocol = config.proxy_protocol;
+        #[cfg(feature = "tls")]
+        {
+            listener.tls = tls;
+        }
+
+        Ok(listener)
+    }
+
+    /// The address resolved by the OS, which differs from the configured one when binding port 0.
+    pub fn local_address(&self) -> &ListenerAddress {
+        &self.local_address
+    }
+
+    /// The TLS state of the listener, if it terminates TLS.
+    #[cfg(feature = "tls")]
+    pub fn tls(&self) -> Option<&Arc<TlsState>> {
+        self.tls.as_ref()
+    }
+
+    async fn accept(&self) -> io::Result<Stream> {
+        match &self.inner {
+            Inner::Tcp(listener) => {
+                let (stream, _remote_address) = listener.accept().await?;
+                Ok(Stream::Tcp(stream))
+            }
+            Inner::Unix(listener) => {
+                let (stream, _remote_address) = listener.accept().await?;
+                Ok(Stream::Unix(stream))
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Some(path) = self.socket_path.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn bind_tcp(address: SocketAddr, ipv6_only: bool) -> io::Result<Listener> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.set_reuse_address(true)?;
+    if address.is_ipv6() {
+        socket.set_only_v6(ipv6_only)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(BACKLOG)?;
+
+    let inner = TcpListener::from_std(socket.into())?;
+    let local_address = inner.local_addr()?;
+
+    Ok(Listener {
+        inner: Inner::Tcp(inner),
+        local_address: ListenerAddress::Tcp(local_address),
+        socket_path: None,
+        proxy_protocol: false,
+        #[cfg(feature = "tls")]
+        tls: None,
+    })
+}
+
+fn bind_unix(path: &Path, config: &ListenerConfig) -> io::Result<Listener> {
+    if config.remove_stale_socket {
+        remove_stale_socket(path)?;
+    }
+
+    let listener = Listener {
+        inner: Inner::Unix(UnixListener::bind(path)?),
+        local_address: ListenerAddress::Unix(path.to_path_buf()),
+        socket_path: Some(path.to_path_buf()),
+        proxy_protocol: false,
+        #[cfg(feature = "tls")]
+        tls: None,
+    };
+
+    // On failure the listener is dropped, which removes the socket we just created.
+    if let Some(mode) = config.unix_mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+
+    Ok(listener)
+}
+
+#[cfg(target_os = "linux")]
+fn bind_unix_abstract(name: &[u8]) -> io::Result<Listener> {
+    use std::os::linux::net::SocketAddrExt;
+
+    let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+    let listener = std::os::unix::net::UnixListener::bind_addr(&address)?;
+    listener.set_nonblocking(true)?;
+
+    Ok(Listener {
+        inner: Inner::Unix(UnixListener::from_std(listener)?),
+        local_address: ListenerAddress::UnixAbstract(name.to_vec()),
+        // Abstract sockets have no filesystem entry, and disappear with their last descriptor.
+        socket_path: None,
+        proxy_protocol: false,
+        #[cfg(feature = "tls")]
+        tls: None,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bind_unix_abstract(_name: &[u8]) -> io::Result<Listener> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "abstract Unix sockets are only supported on Linux",
+    ))
+}
+
+/// Removes the socket at `path` if it was left behind by a process that is no longer listening.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "path exists and is not a socket",
+        ));
+    }
+
+    // A socket nobody listens on refuses connections: only then is it safe to remove.
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "socket is in use by another process",
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path),
+        Err(e) => Err(e),
+    }
+}
+
+/// Accepts connections on `listener` and serves them until `shutdown` turns true.
+/// Each connection task holds a clone of `drain`, so that the caller can await all connections to finish.
+pub async fn serve(
+    listener: Listener,
+    context: Arc<ServerContext>,
+    http: Http,
+    mut shutdown: watch::Receiver<bool>,
+    drain: mpsc::Sender<()>,
+) {
+    loop {
+        // Wait for a free connection slot before accepting, so that excess clients wait in the listen backlog.
+        let slot = match &context.connection_slots {
+            Some(slots) => tokio::select! {
+                slot = slots.clone().acquire_owned() => slot.ok(),
+                _ = stopped(&mut shutdown) => break,
+            },
+            None => None,
+        };
+
+        let stream = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = stopped(&mut shutdown) => break,
+        };
+
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_e) => {
+                #[cfg(feature = "debug")]
+                eprintln!("[Rust] accept error on {}: {_e}", listener.local_address);
+                // Errors like EMFILE are usually transient: back off instead of spinning.
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                continue;
+            }
+        };
+
+        let open_connection = OpenConnection::new(slot, context.stats.clone());
+        let context = context.clone();
+        #[cfg_attr(not(feature = "tls"), allow(unused_mut))]
+        let mut http = http.clone();
+        let mut shutdown = shutdown.clone();
+        let drain = drain.clone();
+        let local_address = listener.local_address.clone();
+        let proxy_protocol = listener.proxy_protocol;
+        #[cfg(feature = "tls")]
+        let tls = listener.tls.clone();
+        #[cfg(feature = "tls")]
+        let tls_enabled = tls.is_some();
+        #[cfg(not(feature = "tls"))]
+        let tls_enabled = false;
+
+        tokio::spawn(async move {
+            let mut stream = stream;
+            #[cfg_attr(not(feature = "tls"), allow(unused_mut))]
+            let mut http2_only = false;
+
+            // The PROXY header precedes the TLS handshake, and is bound by the header-read timeout.
+            let proxy_header = match proxy_protocol {
+                true => {
+                    let header = proxy_protocol::read_header(&mut stream);
+                    let header = match context.connections.header_read_timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, header)
+                            .await
+                            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+                        None => header.await,
+                    };
+                    match header {
+                        Ok(header) => header,
+                        Err(_e) => {
+                            #[cfg(feature = "debug")]
+                            eprintln!("[Rust] invalid PROXY header on {local_address}: {_e}");
+                            return;
+                        }
+                    }
+                }
+                false => ProxyHeader::default(),
+            };
+
+            // The handshake runs on the connection task, so that a slow client never blocks the accept loop.
+            #[cfg(feature = "tls")]
+            let stream = match tls {
+                Some(tls) => {
+                    let handshake = tls.acceptor.accept(stream);
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, handshake).await {
+                        Ok(Ok(stream)) => {
+                            // Clients that negotiated h2 through ALPN skip the HTTP/1 detection.
+                            if TlsState::negotiated_http2(&stream) {
+                                http.http2_only(true);
+                                http2_only = true;
+                            }
+                            Stream::Tls(Box::new(stream))
+                        }
+                        _ => return,
+                    }
+                }
+                None => stream,
+            };
+
+            let connection_info = Arc::new(ConnectionInfo {
+                id: context.next_connection_id.fetch_add(1, Ordering::Relaxed),
+                remote_address: proxy_header.source.or(stream.peer_address()),
+                local_address: match proxy_header.destination.or(stream.local_address()) {
+                    Some(address) => ListenerAddress::Tcp(address),
+                    None => local_address,
+                },
+                tls: tls_enabled,
+            });
+            let activity = Arc::new(ConnectionActivity::new(http2_only));
+            let stream = TrackedStream::new(stream, activity.clone());
+
+            let service_context = context.clone();
+            let service_activity = activity.clone();
+            let service = service_fn(move |request| {
+                let guard = service_activity.begin_request(request.version() == Version::HTTP_2);
+                let response =
+                    server::handle(request, service_context.clone(), connection_info.clone());
+                async move {
+                    let response = response.await;
+                    drop(guard);
+                    response
+                }
+            });
+            let connection = http.serve_connection(stream, service);
+            tokio::pin!(connection);
+
+            tokio::select! {
+                _ = connection.as_mut() => {}
+                _ = stopped(&mut shutdown) => {
+                    // Let in-flight requests finish, but stop accepting new ones on this connection.
+                    connection.as_mut().graceful_shutdown();
+                    let _ = connection.await;
+                }
+                expiry = activity.expired(&context.connections) => {
+                    // A client trickling its request head gets no response, and hyper only closes HTTP/1
+                    // connections gracefully once they served a request: those connections are dropped.
+                    let drop_connection = match expiry {
+                        Expiry::HeaderReadTimeout => true,
+                        Expiry::Idle => !activity.served_any(),
+                        Expiry::MaxRequests => false,
+                    };
+                    if !drop_connection {
+                        connection.as_mut().graceful_shutdown();
+                        let _ = connection.await;
+                    }
+                }
+            }
+
+            drop(open_connection);
+            drop(drain);
+        });
+    }
+}
+
+/// Resolves once the shutdown flag turns true, including when it already was.
+async fn stopped(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
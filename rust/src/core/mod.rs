@@ -1,6 +1,27 @@
+pub mod admission;
 pub mod arc;
+pub mod body;
+pub mod cache;
+pub mod compression;
+pub mod config;
+pub mod config_handle;
+pub mod connection;
+pub mod cors;
+pub mod etag;
+pub mod forwarded;
 pub mod frames;
+pub mod layers;
+pub mod listener;
+pub mod proxy_protocol;
+pub mod range;
+pub mod rate_limit;
+pub mod request_id;
 pub mod router;
 pub mod router_handle;
 pub mod server;
+pub mod static_files;
+pub mod stats;
+pub mod stream;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod types;
@@ -0,0 +1,313 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// The longest v1 header, `PROXY TCP6` with the longest addresses and ports, including the CRLF.
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+const V2_COMMAND_LOCAL: u8 = 0x0;
+const V2_COMMAND_PROXY: u8 = 0x1;
+const V2_FAMILY_TCP4: u8 = 0x11;
+const V2_FAMILY_UDP4: u8 = 0x12;
+const V2_FAMILY_TCP6: u8 = 0x21;
+const V2_FAMILY_UDP6: u8 = 0x22;
+
+/// The connection described by the PROXY protocol header sent by a load balancer.
+/// Addresses are `None` when the balancer connected on its own behalf (e.g. health checks)
+/// or did not describe the connection, in which case the socket addresses apply.
+#[derive(Debug, Default)]
+pub struct ProxyHeader {
+    pub source: Option<SocketAddr>,
+    pub destination: Option<SocketAddr>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Reads the PROXY protocol v1 or v2 header at the start of `stream`, consuming exactly its bytes
+/// so that the stream can be handed to TLS or hyper afterwards. Fails on malformed preambles.
+pub async fn read_header<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<ProxyHeader> {
+    // Both versions are at least 12 bytes long, the shortest v1 header being `PROXY UNKNOWN\r\n`.
+    let mut start = [0u8; 12];
+    stream.read_exact(&mut start).await?;
+
+    if &start == V2_SIGNATURE {
+        return read_v2(stream).await;
+    }
+    if start.starts_with(V1_PREFIX) {
+        return read_v1(stream, &start).await;
+    }
+    return Err(invalid("missing PROXY protocol header"));
+}
+
+/// Reads the rest of `PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\n`, one byte at a time
+/// so that nothing past the CRLF is consumed.
+async fn read_v1<S: AsyncRead + Unpin>(stream: &mut S, start: &[u8]) -> io::Result<ProxyHeader> {
+    let mut line = start.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LEN {
+            return Err(invalid("PROXY v1 header too long"));
+        }
+        line.push(stream.read_u8().await?);
+    }
+
+    let line = std::str::from_utf8(&line[V1_PREFIX.len()..line.len() - 2])
+        .map_err(|_| invalid("PROXY v1 header is not ASCII"))?;
+    let fields: Vec<&str> = line.split(' ').collect();
+
+    let ip = |text: &str, v4: bool| -> io::Result<IpAddr> {
+        let address = IpAddr::from_str(text).map_err(|_| invalid("invalid PROXY v1 address"))?;
+        match address.is_ipv4() == v4 {
+            true => Ok(address),
+            false => Err(invalid("PROXY v1 address does not match its protocol")),
+        }
+    };
+    let port = |text: &str| -> io::Result<u16> {
+        // Ports are decimal without leading zeros.
+        match text.starts_with('0') && text != "0" {
+            true => Err(invalid("invalid PROXY v1 port")),
+            false => u16::from_str(text).map_err(|_| invalid("invalid PROXY v1 port")),
+        }
+    };
+
+    return match fields.as_slice() {
+        // The balancer may not know the protocol, and can then send anything up to the CRLF.
+        ["UNKNOWN", ..] => Ok(ProxyHeader::default()),
+        [
+            protocol @ ("TCP4" | "TCP6"),
+            source,
+            destination,
+            source_port,
+            destination_port,
+        ] => {
+            let v4 = *protocol == "TCP4";
+            Ok(ProxyHeader {
+                source: Some(SocketAddr::new(ip(source, v4)?, port(source_port)?)),
+                destination: Some(SocketAddr::new(
+                    ip(destination, v4)?,
+                    port(destination_port)?,
+                )),
+            })
+        }
+        _ => Err(invalid("malformed PROXY v1 header")),
+    };
+}
+
+/// Reads the rest of a binary v2 header: `[u8 version_command][u8 family][u16 length][addresses][TLVs]`.
+async fn read_v2<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<ProxyHeader> {
+    let version_command = stream.read_u8().await?;
+    let family = stream.read_u8().await?;
+    let length = stream.read_u16().await? as usize;
+
+    // The addresses and TLVs are always consumed, including when they are ignored.
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload).await?;
+
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    match version_command & 0x0f {
+        V2_COMMAND_LOCAL => return Ok(ProxyHeader::default()),
+        V2_COMMAND_PROXY => {}
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+
+    return match family {
+        V2_FAMILY_TCP4 | V2_FAMILY_UDP4 => {
+            let Some(addresses) = payload.get(..12) else {
+                return Err(invalid("truncated PROXY v2 IPv4 addresses"));
+            };
+            let ip = |offset: usize| {
+                let octets: [u8; 4] = addresses[offset..offset + 4].try_into().unwrap();
+                IpAddr::V4(Ipv4Addr::from(octets))
+            };
+            let port =
+                |offset: usize| u16::from_be_bytes([addresses[offset], addresses[offset + 1]]);
+
+            Ok(ProxyHeader {
+                source: Some(SocketAddr::new(ip(0), port(8))),
+                destination: Some(SocketAddr::new(ip(4), port(10))),
+            })
+        }
+        V2_FAMILY_TCP6 | V2_FAMILY_UDP6 => {
+            let Some(addresses) = payload.get(..36) else {
+                return Err(invalid("truncated PROXY v2 IPv6 addresses"));
+            };
+            let ip = |offset: usize| {
+                let octets: [u8; 16] = addresses[offset..offset + 16].try_into().unwrap();
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            let port =
+                |offset: usize| u16::from_be_bytes([addresses[offset], addresses[offset + 1]]);
+
+            Ok(ProxyHeader {
+                source: Some(SocketAddr::new(ip(0), port(32))),
+                destination: Some(SocketAddr::new(ip(16), port(34))),
+            })
+        }
+        // Unspecified and Unix socket families carry no IP address to report.
+        _ => Ok(ProxyHeader::default()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        net::{Ipv6Addr, SocketAddr},
+    };
+
+    use super::{ProxyHeader, V2_SIGNATURE, read_header};
+
+    /// Reads the header at the start of `bytes`, returning it with the bytes left after it.
+    async fn parse(bytes: &[u8]) -> (io::Result<ProxyHeader>, Vec<u8>) {
+        let mut stream = bytes;
+        let header = read_header(&mut stream).await;
+        return (header, stream.to_vec());
+    }
+
+    fn address(text: &str) -> Option<SocketAddr> {
+        return Some(text.parse().expect("socket address"));
+    }
+
+    fn v2(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.push(0x20 | command);
+        bytes.push(family);
+        bytes.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(payload);
+        return bytes;
+    }
+
+    fn error_kind(header: io::Result<ProxyHeader>) -> io::ErrorKind {
+        return header.expect_err("malformed header").kind();
+    }
+
+    #[tokio::test]
+    async fn v1_headers_describe_the_connection() {
+        let (header, rest) = parse(b"PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\nGET").await;
+        let header = header.expect("header");
+        assert_eq!(header.source, address("203.0.113.7:56324"));
+        assert_eq!(header.destination, address("10.0.0.1:443"));
+        assert_eq!(rest, b"GET");
+
+        let (header, _) = parse(b"PROXY TCP6 2001:db8::1 2001:db8::2 4711 443\r\n").await;
+        let header = header.expect("header");
+        assert_eq!(header.source, address("[2001:db8::1]:4711"));
+        assert_eq!(header.destination, address("[2001:db8::2]:443"));
+
+        let (header, rest) = parse(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\nGET").await;
+        let header = header.expect("header");
+        assert!(header.source.is_none() && header.destination.is_none());
+        assert_eq!(rest, b"GET");
+    }
+
+    #[tokio::test]
+    async fn malformed_v1_headers_are_rejected() {
+        for line in [
+            &b"PROXY TCP4 2001:db8::1 10.0.0.1 1 2\r\n"[..],
+            b"PROXY TCP4 203.0.113.7 10.0.0.1 056324 443\r\n",
+            b"PROXY TCP4 203.0.113.7 10.0.0.1 65536 443\r\n",
+            b"PROXY TCP4 203.0.113.7 10.0.0.1 56324\r\n",
+            b"PROXY UDP4 203.0.113.7 10.0.0.1 56324 443\r\n",
+        ] {
+            let (header, _) = parse(line).await;
+            assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+        }
+
+        let long = format!("PROXY UNKNOWN {}\r\n", "x".repeat(100));
+        let (header, _) = parse(long.as_bytes()).await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn v2_headers_describe_the_connection() {
+        let mut inet = vec![203, 0, 113, 7, 10, 0, 0, 1];
+        inet.extend_from_slice(&56324u16.to_be_bytes());
+        inet.extend_from_slice(&443u16.to_be_bytes());
+        let mut bytes = v2(0x1, 0x11, &inet);
+        bytes.extend_from_slice(b"GET");
+        let (header, rest) = parse(&bytes).await;
+        let header = header.expect("header");
+        assert_eq!(header.source, address("203.0.113.7:56324"));
+        assert_eq!(header.destination, address("10.0.0.1:443"));
+        assert_eq!(rest, b"GET");
+
+        let mut inet6 = Vec::new();
+        inet6.extend_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).octets());
+        inet6.extend_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2).octets());
+        inet6.extend_from_slice(&4711u16.to_be_bytes());
+        inet6.extend_from_slice(&443u16.to_be_bytes());
+        // TLVs after the addresses are skipped.
+        inet6.extend_from_slice(&[0x04, 0x00, 0x01, 0xff]);
+        let (header, rest) = parse(&v2(0x1, 0x21, &inet6)).await;
+        let header = header.expect("header");
+        assert_eq!(header.source, address("[2001:db8::1]:4711"));
+        assert_eq!(header.destination, address("[2001:db8::2]:443"));
+        assert!(rest.is_empty());
+
+        let (header, _) = parse(&v2(0x1, 0x31, &[0u8; 216])).await;
+        let header = header.expect("header");
+        assert!(header.source.is_none() && header.destination.is_none());
+    }
+
+    #[tokio::test]
+    async fn v2_local_headers_keep_the_socket_addresses() {
+        for (family, length) in [(0x11, 12), (0x21, 36), (0x31, 216)] {
+            let mut bytes = v2(0x0, family, &vec![1u8; length]);
+            bytes.extend_from_slice(b"GET");
+            let (header, rest) = parse(&bytes).await;
+            let header = header.expect("header");
+            assert!(header.source.is_none() && header.destination.is_none());
+            assert_eq!(rest, b"GET");
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_v2_headers_are_rejected() {
+        // Addresses shorter than their family.
+        let (header, _) = parse(&v2(0x1, 0x11, &[0u8; 4])).await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+        let (header, _) = parse(&v2(0x1, 0x21, &[0u8; 12])).await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+
+        // Unknown command and version.
+        let (header, _) = parse(&v2(0x2, 0x11, &[0u8; 12])).await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+        let mut bytes = v2(0x1, 0x11, &[0u8; 12]);
+        bytes[12] = 0x11;
+        let (header, _) = parse(&bytes).await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+
+        // A length beyond the bytes sent.
+        let mut bytes = v2(0x1, 0x11, &[0u8; 12]);
+        bytes[14..16].copy_from_slice(&1000u16.to_be_bytes());
+        let (header, _) = parse(&bytes).await;
+        assert_eq!(error_kind(header), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn truncated_and_unsigned_headers_are_rejected() {
+        let (header, _) = parse(b"PROXY TCP4 203.0.113.7").await;
+        assert_eq!(error_kind(header), io::ErrorKind::UnexpectedEof);
+        let (header, _) = parse(b"PROXY").await;
+        assert_eq!(error_kind(header), io::ErrorKind::UnexpectedEof);
+        let (header, _) = parse(&V2_SIGNATURE[..]).await;
+        assert_eq!(error_kind(header), io::ErrorKind::UnexpectedEof);
+
+        let (header, _) = parse(b"GET / HTTP/1.1\r\n\r\n").await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+        let mut bytes = v2(0x1, 0x11, &[0u8; 12]);
+        bytes[11] = b'X';
+        let (header, _) = parse(&bytes).await;
+        assert_eq!(error_kind(header), io::ErrorKind::InvalidData);
+    }
+}
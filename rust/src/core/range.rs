@@ -0,0 +1,411 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::RangeInclusive,
+    time::SystemTime,
+};
+
+use hyper::{
+    Body, Request, Response, StatusCode,
+    header::{
+        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, HeaderValue, IF_RANGE,
+        LAST_MODIFIED, RANGE,
+    },
+};
+
+/// The most ranges served in a multipart response, beyond which the full representation is sent.
+const MAX_RANGES: usize = 16;
+
+/// The ranges requested by a `Range` header, resolved against a representation of known length.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// The header is absent, malformed, or not in bytes: the full representation is sent.
+    Full,
+    /// The satisfiable ranges, in ascending order, overlapping and adjacent ones coalesced.
+    Ranges(Vec<RangeInclusive<u64>>),
+    /// None of the ranges overlaps the representation, answered with 416 Range Not Satisfiable.
+    Unsatisfiable,
+}
+
+/// Parses `bytes=0-499`, `bytes=500-`, `bytes=-500` and lists of them against a `len` bytes representation.
+pub fn parse(header: &str, len: u64) -> RangeRequest {
+    let Some(specs) = header.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',').map(|spec| spec.trim()) {
+        let Some((first, last)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+        let first = first.trim();
+        let last = last.trim();
+
+        let range = match (first.is_empty(), last.is_empty()) {
+            // A suffix of the last `n` bytes.
+            (true, false) => {
+                let Ok(suffix) = last.parse::<u64>() else {
+                    return RangeRequest::Full;
+                };
+                (suffix > 0 && len > 0).then(|| len.saturating_sub(suffix)..=len - 1)
+            }
+            (false, _) => {
+                let Ok(first) = first.parse::<u64>() else {
+                    return RangeRequest::Full;
+                };
+                let last = match last.is_empty() {
+                    true => u64::MAX,
+                    false => match last.parse::<u64>() {
+                        Ok(last) if last >= first => last,
+                        _ => return RangeRequest::Full,
+                    },
+                };
+                (first < len).then(|| first..=last.min(len - 1))
+            }
+            (true, true) => return RangeRequest::Full,
+        };
+
+        ranges.extend(range);
+    }
+
+    return match ranges.is_empty() {
+        true => RangeRequest::Unsatisfiable,
+        false => RangeRequest::Ranges(coalesce(ranges)),
+    };
+}
+
+/// Merges overlapping and adjacent ranges, as RFC 9110 allows, so that requests like `bytes=0-,0-,0-`
+/// cannot have the same bytes sent several times.
+fn coalesce(mut ranges: Vec<RangeInclusive<u64>>) -> Vec<RangeInclusive<u64>> {
+    ranges.sort_by_key(|range| *range.start());
+
+    let mut coalesced: Vec<RangeInclusive<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                *last = *last.start()..=*last.end().max(range.end());
+            }
+            _ => coalesced.push(range),
+        }
+    }
+
+    return coalesced;
+}
+
+/// Whether the `If-Range` validator still matches the representation, in which case the ranges apply.
+/// Entity tags match by strong comparison, and dates only when exactly equal to the modification time.
+pub fn if_range_matches(
+    if_range: &str,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') {
+        return etag.is_some_and(|etag| !etag.starts_with("W/") && etag == if_range);
+    }
+    if if_range.starts_with("W/") {
+        return false;
+    }
+
+    return match (httpdate::parse_http_date(if_range), last_modified) {
+        (Ok(date), Some(last_modified)) => date == truncate_to_seconds(last_modified),
+        _ => false,
+    };
+}
+
+/// Drops the sub-second part of `time`, as HTTP dates only have a precision of one second.
+pub fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    return match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since_epoch) => {
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(since_epoch.as_secs())
+        }
+        Err(_) => time,
+    };
+}
+
+/// The `Content-Range` of `range` within a `len` bytes representation.
+pub fn content_range(range: &RangeInclusive<u64>, len: u64) -> String {
+    return format!("bytes {}-{}/{}", range.start(), range.end(), len);
+}
+
+/// The `Range` and `If-Range` headers of a request, kept once its body is consumed.
+pub struct RangeHeaders {
+    range: String,
+    if_range: Option<String>,
+}
+
+impl RangeHeaders {
+    /// The range headers of a GET request that has a `Range` header.
+    pub fn from_request(request: &Request<Body>) -> Option<RangeHeaders> {
+        if request.method() != hyper::Method::GET {
+            return None;
+        }
+
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        return Some(RangeHeaders {
+            range: header(RANGE)?,
+            if_range: header(IF_RANGE),
+        });
+    }
+
+    /// Narrows a 200 response declaring `Accept-Ranges: bytes` with its full `body` to the requested ranges:
+    /// 206 with `Content-Range` for one range, `multipart/byteranges` for several, and 416 when none is
+    /// satisfiable. Returns the body to send.
+    pub fn apply(&self, response: &mut Response<Body>, body: Vec<u8>) -> Vec<u8> {
+        let headers = response.headers();
+        let rangeable = headers
+            .get(ACCEPT_RANGES)
+            .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"bytes"));
+        if response.status() != StatusCode::OK || !rangeable {
+            return body;
+        }
+
+        if let Some(if_range) = &self.if_range {
+            let etag = headers.get(ETAG).and_then(|value| value.to_str().ok());
+            let last_modified = headers
+                .get(LAST_MODIFIED)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| httpdate::parse_http_date(value).ok());
+            if !if_range_matches(if_range, etag, last_modified) {
+                return body;
+            }
+        }
+
+        let len = body.len() as u64;
+        let ranges = match parse(&self.range, len) {
+            RangeRequest::Full => return body,
+            RangeRequest::Unsatisfiable => {
+                *response.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+                let headers = response.headers_mut();
+                headers.remove(CONTENT_LENGTH);
+                if let Ok(content_range) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                    headers.insert(CONTENT_RANGE, content_range);
+                }
+                return Vec::new();
+            }
+            RangeRequest::Ranges(ranges) if ranges.len() > MAX_RANGES => return body,
+            RangeRequest::Ranges(ranges) => ranges,
+        };
+
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        let headers = response.headers_mut();
+        headers.remove(CONTENT_LENGTH);
+
+        let slice =
+            |range: &RangeInclusive<u64>| &body[*range.start() as usize..=*range.end() as usize];
+
+        if let [range] = ranges.as_slice() {
+            if let Ok(content_range) = HeaderValue::from_str(&content_range(range, len)) {
+                headers.insert(CONTENT_RANGE, content_range);
+            }
+            return slice(range).to_vec();
+        }
+
+        // Each part repeats the media type of the representation, replaced by the multipart one.
+        let boundary = format!("{:016x}", RandomState::new().build_hasher().finish());
+        let content_type = headers.remove(CONTENT_TYPE);
+        if let Ok(multipart) =
+            HeaderValue::from_str(&format!("multipart/byteranges; boundary={}", boundary))
+        {
+            headers.insert(CONTENT_TYPE, multipart);
+        }
+
+        let mut multipart = Vec::new();
+        for range in &ranges {
+            multipart.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            if let Some(content_type) = &content_type {
+                multipart.extend_from_slice(b"Content-Type: ");
+                multipart.extend_from_slice(content_type.as_bytes());
+                multipart.extend_from_slice(b"\r\n");
+            }
+            multipart.extend_from_slice(
+                format!("Content-Range: {}\r\n\r\n", content_range(range, len)).as_bytes(),
+            );
+            multipart.extend_from_slice(slice(range));
+            multipart.extend_from_slice(b"\r\n");
+        }
+        multipart.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        return multipart;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use hyper::{
+        Body, Request, Response, StatusCode,
+        header::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, LAST_MODIFIED},
+    };
+
+    use super::{RangeHeaders, RangeRequest, if_range_matches, parse};
+
+    const BODY: &[u8] = b"0123456789";
+
+    /// Applies `Range: range` and `If-Range: if_range` to a 200 response with `BODY`, an entity tag and a date.
+    fn apply(range: &str, if_range: Option<&str>) -> (Response<Body>, Vec<u8>) {
+        let mut request = Request::get("/").header("range", range);
+        if let Some(if_range) = if_range {
+            request = request.header("if-range", if_range);
+        }
+        let request = request.body(Body::empty()).expect("request");
+        let headers = RangeHeaders::from_request(&request).expect("range headers");
+
+        let mut response = Response::new(Body::empty());
+        let response_headers = response.headers_mut();
+        response_headers.insert(ACCEPT_RANGES, "bytes".parse().expect("value"));
+        response_headers.insert(CONTENT_TYPE, "text/plain".parse().expect("value"));
+        response_headers.insert(ETAG, "\"v1\"".parse().expect("value"));
+        response_headers.insert(
+            LAST_MODIFIED,
+            "Sun, 06 Nov 1994 08:49:37 GMT".parse().expect("value"),
+        );
+        let body = headers.apply(&mut response, BODY.to_vec());
+        return (response, body);
+    }
+
+    #[test]
+    fn open_ended_and_suffix_ranges_are_resolved_against_the_length() {
+        assert_eq!(parse("bytes=0-", 1000), RangeRequest::Ranges(vec![0..=999]));
+        assert_eq!(
+            parse("bytes=990-", 1000),
+            RangeRequest::Ranges(vec![990..=999])
+        );
+        assert_eq!(
+            parse("bytes=-500", 1000),
+            RangeRequest::Ranges(vec![500..=999])
+        );
+        assert_eq!(
+            parse("bytes=-5000", 1000),
+            RangeRequest::Ranges(vec![0..=999])
+        );
+        assert_eq!(
+            parse("bytes=0-499", 1000),
+            RangeRequest::Ranges(vec![0..=499])
+        );
+        assert_eq!(
+            parse("bytes=500-5000", 1000),
+            RangeRequest::Ranges(vec![500..=999])
+        );
+    }
+
+    #[test]
+    fn ranges_past_the_end_are_unsatisfiable() {
+        assert_eq!(parse("bytes=1000-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(
+            parse("bytes=1000-2000, 3000-", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(parse("bytes=-0", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse("bytes=0-", 0), RangeRequest::Unsatisfiable);
+        assert_eq!(parse("bytes=-1", 0), RangeRequest::Unsatisfiable);
+
+        // Satisfiable ranges are kept, the others dropped.
+        assert_eq!(
+            parse("bytes=2000-, 0-9", 1000),
+            RangeRequest::Ranges(vec![0..=9])
+        );
+
+        let (response, body) = apply("bytes=10-", None);
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes */10");
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_ranges_are_coalesced() {
+        assert_eq!(
+            parse("bytes=0-,0-,0-", 1000),
+            RangeRequest::Ranges(vec![0..=999])
+        );
+        assert_eq!(
+            parse("bytes=500-599, 0-99, 50-149, 150-199, 201-300", 1000),
+            RangeRequest::Ranges(vec![0..=199, 201..=300, 500..=599])
+        );
+        assert_eq!(
+            parse("bytes=-100, 850-900", 1000),
+            RangeRequest::Ranges(vec![850..=999])
+        );
+    }
+
+    #[test]
+    fn malformed_ranges_get_the_full_representation() {
+        for header in [
+            "items=0-9",
+            "bytes 0-9",
+            "bytes=",
+            "bytes=-",
+            "bytes=a-9",
+            "bytes=0-b",
+            "bytes=9-0",
+            "bytes=0-9,",
+            "bytes=0-9, x",
+            "bytes=--5",
+            "bytes=18446744073709551616-",
+        ] {
+            assert_eq!(parse(header, 1000), RangeRequest::Full, "{:?}", header);
+        }
+
+        let (response, body) = apply("bytes=9-0", None);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body, BODY);
+    }
+
+    #[test]
+    fn ranges_narrow_the_response() {
+        let (response, body) = apply("bytes=2-4", None);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(body, b"234");
+
+        let (response, body) = apply("bytes=0-1, 8-", None);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response.headers()[CONTENT_TYPE].to_str().expect("value");
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let body = String::from_utf8(body).expect("UTF-8");
+        assert!(body.contains("Content-Range: bytes 0-1/10\r\n\r\n01\r\n"));
+        assert!(body.contains("Content-Range: bytes 8-9/10\r\n\r\n89\r\n"));
+        assert_eq!(body.matches("Content-Type: text/plain").count(), 2);
+    }
+
+    #[test]
+    fn stale_if_range_validators_get_the_full_representation() {
+        let (response, body) = apply("bytes=2-4", Some("\"v1\""));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"234");
+        let (response, body) = apply("bytes=2-4", Some("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"234");
+
+        for stale in [
+            "\"v0\"",
+            "W/\"v1\"",
+            "Sun, 06 Nov 1994 08:49:36 GMT",
+            "Sun, 06 Nov 1994 08:49:38 GMT",
+            "yesterday",
+        ] {
+            let (response, body) = apply("bytes=2-4", Some(stale));
+            assert_eq!(response.status(), StatusCode::OK, "{:?}", stale);
+            assert_eq!(body, BODY);
+        }
+    }
+
+    #[test]
+    fn if_range_compares_entity_tags_strongly_and_dates_exactly() {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+        assert!(if_range_matches(date, None, Some(modified)));
+        assert!(!if_range_matches(date, None, None));
+        assert!(if_range_matches("\"a\"", Some("\"a\""), None));
+        assert!(!if_range_matches("\"a\"", Some("W/\"a\""), None));
+        assert!(!if_range_matches("\"a\"", None, Some(modified)));
+    }
+}
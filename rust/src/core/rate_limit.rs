@@ -0,0 +1,203 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    Body, Request, Response,
+    header::{HeaderName, RETRY_AFTER},
+};
+
+use crate::core::{forwarded::ClientInfo, types::HandlerId};
+
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// What requests share a token bucket.
+#[derive(Clone, Debug)]
+pub enum RateLimitKey {
+    /// One bucket per client IP address, as resolved through trusted proxies. Clients of Unix domain sockets share a single bucket.
+    ClientIp,
+    /// One bucket per value of the header, e.g. an API key. Requests without it fall back to their client IP.
+    Header(HeaderName),
+    /// One bucket per route, shared by all clients.
+    Route,
+}
+
+/// A token bucket holding up to `capacity` tokens, refilled at `refill_per_second`. Each request takes a token.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    pub key: RateLimitKey,
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum BucketKey {
+    Ip(IpAddr),
+    Local,
+    Header(Vec<u8>),
+    Route(HandlerId),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket was last updated, as a position in `Buckets::recency`.
+    used: u64,
+}
+
+#[derive(Default)]
+struct Buckets {
+    entries: HashMap<BucketKey, Bucket>,
+    /// The keys of the buckets, from the least to the most recently updated.
+    recency: BTreeMap<u64, BucketKey>,
+    next_use: u64,
+}
+
+impl Buckets {
+    /// Forgets the least recently updated bucket, returning whether there was one.
+    fn remove_oldest(&mut self) -> bool {
+        let Some((_, key)) = self.recency.pop_first() else {
+            return false;
+        };
+        self.entries.remove(&key);
+        return true;
+    }
+}
+
+/// The state of a bucket after a request took, or failed to take, a token from it.
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again, as sent in `RateLimit-Reset`.
+    pub reset: u64,
+    /// Seconds until the next token, as sent in `Retry-After` when rejected.
+    pub retry_after: u64,
+}
+
+/// The buckets of one `RateLimit`.
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimitDecision {
+    /// Adds the `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers to `response`.
+    pub fn apply(&self, response: &mut Response<Body>) {
+        let headers = response.headers_mut();
+        headers.insert(RATE_LIMIT_LIMIT, self.limit.into());
+        headers.insert(RATE_LIMIT_REMAINING, self.remaining.into());
+        headers.insert(RATE_LIMIT_RESET, self.reset.into());
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, self.retry_after.into());
+        }
+    }
+}
+
+impl RateLimiter {
+    /// The most buckets kept at once. Beyond it, the least recently updated ones are forgotten,
+    /// so that clients rotating addresses or keys cannot grow the map without bound.
+    const MAX_BUCKETS: usize = 65_536;
+
+    pub fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            limit,
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    /// Takes a token from the bucket of the request.
+    pub fn check(
+        &self,
+        request: &Request<Body>,
+        client: &ClientInfo,
+        handler_id: HandlerId,
+    ) -> RateLimitDecision {
+        let key = self.bucket_key(request, client, handler_id);
+        let capacity = self.limit.capacity as f64;
+        let rate = self.limit.refill_per_second;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Buckets left alone for as long as it takes to fill one are full, and behave the same as new ones.
+        // They are the least recently updated, so each is looked at once before being forgotten.
+        let fill_time = Duration::try_from_secs_f64(capacity / rate).unwrap_or(Duration::MAX);
+        while let Some(oldest) = buckets.recency.first_key_value().map(|(_, key)| key)
+            && buckets
+                .entries
+                .get(oldest)
+                .is_none_or(|bucket| now.duration_since(bucket.updated) >= fill_time)
+        {
+            buckets.remove_oldest();
+        }
+
+        if !buckets.entries.contains_key(&key) {
+            while buckets.entries.len() >= RateLimiter::MAX_BUCKETS && buckets.remove_oldest() {}
+        }
+
+        let used = buckets.next_use;
+        buckets.next_use += 1;
+        if let Some(bucket) = buckets.entries.get(&key) {
+            let previous_use = bucket.used;
+            buckets.recency.remove(&previous_use);
+        }
+        buckets.recency.insert(used, key.clone());
+
+        let bucket = buckets.entries.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            used,
+        });
+        bucket.tokens = Self::refilled(bucket, now, rate, capacity);
+        bucket.updated = now;
+        bucket.used = used;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        // Rounds up, so that clients retrying after the delay find the tokens they wait for.
+        let seconds_until = |tokens: f64| (tokens.max(0.0) / rate).ceil() as u64;
+
+        return RateLimitDecision {
+            allowed,
+            limit: self.limit.capacity,
+            remaining: bucket.tokens.floor() as u32,
+            reset: seconds_until(capacity - bucket.tokens),
+            retry_after: seconds_until(1.0 - bucket.tokens),
+        };
+    }
+
+    fn refilled(bucket: &Bucket, now: Instant, rate: f64, capacity: f64) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        return (bucket.tokens + elapsed * rate).min(capacity);
+    }
+
+    fn bucket_key(
+        &self,
+        request: &Request<Body>,
+        client: &ClientInfo,
+        handler_id: HandlerId,
+    ) -> BucketKey {
+        let client = || match client.address {
+            Some(address) => BucketKey::Ip(address.ip().to_canonical()),
+            None => BucketKey::Local,
+        };
+
+        return match &self.limit.key {
+            RateLimitKey::ClientIp => client(),
+            RateLimitKey::Header(name) => match request.headers().get(name) {
+                Some(value) => BucketKey::Header(value.as_bytes().to_vec()),
+                None => client(),
+            },
+            RateLimitKey::Route => BucketKey::Route(handler_id),
+        };
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use hyper::{Body, Request, header::HeaderValue};
+
+use crate::core::config::RequestIds;
+
+/// The longest incoming request id that is trusted, so that clients cannot flood logs through it.
+const MAX_INCOMING_LENGTH: usize = 128;
+
+/// The id of a request, echoed in its response and passed along with it to Swift.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub HeaderValue);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_str().unwrap_or_default())
+    }
+}
+
+/// Generates the ids of the requests of a server: a random prefix drawn when it starts,
+/// followed by a counter, so that ids are unique within a server and unlikely to collide across servers.
+pub struct RequestIdGenerator {
+    prefix: u64,
+    next: AtomicU64,
+}
+
+impl Default for RequestIdGenerator {
+    fn default() -> Self {
+        RequestIdGenerator {
+            prefix: RandomState::new().build_hasher().finish(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl RequestIdGenerator {
+    /// The id of `request`: its incoming id when `config` trusts it and it is well-formed, a new one otherwise.
+    pub fn resolve(&self, request: &Request<Body>, config: &RequestIds) -> RequestId {
+        if config.trust_incoming
+            && let Some(incoming) = request.headers().get(&config.header)
+            && is_well_formed(incoming)
+        {
+            return RequestId(incoming.clone());
+        }
+
+        let sequence = self.next.fetch_add(1, Ordering::Relaxed);
+        let id = format!("{:016x}{:016x}", self.prefix, sequence);
+        return RequestId(HeaderValue::from_str(&id).expect("hex digits are a valid header value"));
+    }
+}
+
+/// Whether an incoming id is short and made of visible ASCII characters only, so that it is safe to log.
+fn is_well_formed(id: &HeaderValue) -> bool {
+    let bytes = id.as_bytes();
+    return !bytes.is_empty()
+        && bytes.len() <= MAX_INCOMING_LENGTH
+        && bytes.iter().all(|byte| byte.is_ascii_graphic());
+}
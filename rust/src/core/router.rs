@@ -25,9 +25,24 @@ pub fn matches(pattern: &str, path: &str) -> bool {
     return true;
 }
 
+/// The method of requests that no route handles.
+pub(crate) const UNSUPPORTED_METHOD: u8 = 255;
+
 pub fn method_to_u8(m: &hyper::Method) -> u8 {
     match *m {
         hyper::Method::GET => 0,
-        _ => 255,
+        _ => UNSUPPORTED_METHOD,
+    }
+}
+
+/// Encodes the HTTP version as `major * 10 + minor`, e.g. 11 for HTTP/1.1 and 20 for HTTP/2.
+pub fn version_to_u8(v: hyper::Version) -> u8 {
+    match v {
+        hyper::Version::HTTP_09 => 9,
+        hyper::Version::HTTP_10 => 10,
+        hyper::Version::HTTP_11 => 11,
+        hyper::Version::HTTP_2 => 20,
+        hyper::Version::HTTP_3 => 30,
+        _ => 0,
     }
 }
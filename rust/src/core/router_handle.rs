@@ -2,10 +2,12 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 use tokio::sync::RwLock;
 
-use crate::core::types::Route;
+use crate::core::types::{PrefixLayer, Route, RouteTable};
 
 pub struct RouterHandle {
     pub routes: RwLock<Vec<Route>>,
+    /// The layers attached to a path prefix, in the order they were attached.
+    pub prefix_layers: RwLock<Vec<PrefixLayer>>,
     pub frozen: AtomicBool,
 }
 
@@ -13,6 +15,7 @@ impl RouterHandle {
     pub fn new() -> RouterHandle {
         RouterHandle {
             routes: RwLock::new(Vec::new()),
+            prefix_layers: RwLock::new(Vec::new()),
             frozen: AtomicBool::new(false),
         }
     }
@@ -26,4 +29,18 @@ impl RouterHandle {
     pub fn is_frozen(&self) -> bool {
         self.frozen.load(Ordering::Acquire)
     }
+
+    /// Returns a copy of the routes and prefix layers.
+    pub fn snapshot(&self) -> RouteTable {
+        return RouteTable {
+            routes: self.routes.blocking_read().clone(),
+            prefix_layers: self.prefix_layers.blocking_read().clone(),
+        };
+    }
+}
+
+impl Default for RouterHandle {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -1,22 +1,47 @@
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::mpsc,
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc,
+    },
     thread::{self, JoinHandle},
+    time::Instant,
 };
 
-use hyper::{
-    Body, Request, Response, Server,
-    service::{make_service_fn, service_fn},
-};
-use tokio::sync::oneshot;
+use hyper::{Body, Request, Response, body::Bytes, server::conn::Http};
+use tokio::sync::{Semaphore, mpsc as tokio_mpsc, oneshot, watch};
 
+#[cfg(feature = "tls")]
+use crate::core::tls::TlsState;
 use crate::{
     core::{
-        frames, router,
-        types::{Port, SharedRoutes},
+        admission::{DispatchGate, LoadShedder},
+        body::{self, BodyError},
+        cache::Cache,
+        compression::{self, DecompressError},
+        config::{
+            Compression, ConnectionLimits, Decompression, ListenerAddress, RequestIds,
+            RequestLimits, RuntimeConfig, ServerConfig,
+        },
+        connection::ConnectionInfo,
+        cors::Cors,
+        etag::ConditionalHeaders,
+        forwarded::{self, Cidr, ClientInfo},
+        frames,
+        layers::{self, LayerContext},
+        listener::{self, Listener},
+        range::RangeHeaders,
+        rate_limit::{RateLimitDecision, RateLimiter},
+        request_id::{RequestId, RequestIdGenerator},
+        router,
+        static_files::{self, StaticMount},
+        stats::ServerStats,
+        types::{HandlerId, RouteOptions, SharedRoutes},
     },
-    error::set_last_error,
+    error::KiriError,
     runtime::dispatch,
+    status::KiriStatus,
 };
 
 #[cfg(feature = "bench")]
@@ -28,39 +53,159 @@ pub struct ServerHandle {
     pub shutdown_transmitter: Option<oneshot::Sender<()>>,
     pub join: Option<JoinHandle<()>>,
     pub routes: SharedRoutes,
+    /// The local addresses the server is listening on, as resolved by the OS (e.g. when binding port 0).
+    pub bound_addresses: Vec<ListenerAddress>,
+    pub stats: Arc<ServerStats>,
+    /// The TLS state of the listeners that terminate TLS, used to reload their certificates.
+    #[cfg(feature = "tls")]
+    pub tls: Vec<Arc<TlsState>>,
+}
+
+/// What the server thread reports once all its listeners are bound.
+pub struct Started {
+    pub bound_addresses: Vec<ListenerAddress>,
+    #[cfg(feature = "tls")]
+    pub tls: Vec<Arc<TlsState>>,
+}
+
+/// A listener that could not be bound, identified by its index in `ServerConfig::listeners`.
+#[derive(Debug)]
+pub struct ListenerError {
+    pub index: usize,
+    pub error: KiriError,
+}
+
+/// The reason a server failed to start, with the individual failure of each listener if any.
+#[derive(Debug)]
+pub struct StartupError {
+    pub error: KiriError,
+    pub listener_errors: Vec<ListenerError>,
+}
+
+impl StartupError {
+    pub fn new(error: KiriError) -> StartupError {
+        StartupError {
+            error,
+            listener_errors: Vec::new(),
+        }
+    }
+}
+
+/// The state shared by every connection of a running server.
+pub struct ServerContext {
+    pub routes: SharedRoutes,
+    pub limits: RequestLimits,
+    pub connections: ConnectionLimits,
+    /// One permit per open connection when `ConnectionLimits::max_connections` is set.
+    pub connection_slots: Option<Arc<Semaphore>>,
+    pub dispatch: DispatchGate,
+    pub load_shedder: LoadShedder,
+    /// The value of the `Retry-After` header of 503 responses, in seconds.
+    pub retry_after: u64,
+    pub rate_limiter: Option<RateLimiter>,
+    /// The rate limiters of the routes with their own rate limit.
+    pub route_rate_limiters: HashMap<HandlerId, RateLimiter>,
+    pub trusted_proxies: Vec<Cidr>,
+    pub compression: Option<Compression>,
+    pub decompression: Option<Decompression>,
+    pub cache: Option<Cache>,
+    /// The CORS policy of every route without its own.
+    pub cors: Option<Arc<Cors>>,
+    pub request_ids: RequestIds,
+    pub request_id_generator: RequestIdGenerator,
+    pub next_connection_id: AtomicU64,
+    pub stats: Arc<ServerStats>,
 }
 
-async fn handle(
+pub async fn handle(
     request: Request<Body>,
-    routes: SharedRoutes,
+    context: Arc<ServerContext>,
+    connection: Arc<ConnectionInfo>,
 ) -> Result<Response<Body>, hyper::Error> {
-    let method = router::method_to_u8(request.method());
+    // Every response echoes the id of its request, including the ones answered by Rust.
+    let request_id = context
+        .request_id_generator
+        .resolve(&request, &context.request_ids);
+    let header = context.request_ids.header.clone();
+
+    let mut response = handle_request(request, &request_id, context, connection).await?;
+    response.headers_mut().insert(header, request_id.0);
+    return Ok(response);
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    request_id: &RequestId,
+    context: Arc<ServerContext>,
+    connection: Arc<ConnectionInfo>,
+) -> Result<Response<Body>, hyper::Error> {
+    let received = Instant::now();
+    if let Some(response) = check_head_limits(&request, &context) {
+        return Ok(response);
+    }
+
     let path = request.uri().path().to_string();
 
-    let handler_id = routes
-        .read()
-        .await
-        .iter()
-        .find(|r| r.method == method && router::matches(&r.pattern, &path))
-        .map(|r| r.handler_id);
+    // Preflight requests ask about the method of the request they precede, and get the policy of its route.
+    let preflight = Cors::is_preflight(&request);
+    let method = match preflight {
+        true => request
+            .headers()
+            .get(hyper::header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|value| hyper::Method::from_bytes(value.as_bytes()).ok())
+            .map_or(router::UNSUPPORTED_METHOD, |method| {
+                router::method_to_u8(&method)
+            }),
+        false => router::method_to_u8(request.method()),
+    };
+    let (route, path_routed, prefix_layers) = {
+        let table = context.routes.read().await;
+        let route = table
+            .routes
+            .iter()
+            .find(|r| match &r.mount {
+                Some(mount) => mount.matches(&path),
+                None => r.method == method && router::matches(&r.pattern, &path),
+            })
+            .map(|r| MatchedRoute {
+                handler_id: r.handler_id,
+                options: r.options.clone(),
+                mount: r.mount.clone(),
+            });
+        let path_routed = preflight
+            && table
+                .routes
+                .iter()
+                .any(|r| r.mount.is_none() && router::matches(&r.pattern, &path));
+        // Prefixes are matched against the path itself, whatever route or mount serves it.
+        let prefix_layers: Vec<_> = table
+            .prefix_layers
+            .iter()
+            .filter(|prefix_layer| layers::is_under_prefix(&path, &prefix_layer.prefix))
+            .map(|prefix_layer| prefix_layer.layer.clone())
+            .collect();
+        (route, path_routed, prefix_layers)
+    };
 
-    let handler_id = match handler_id {
-        Some(id) => id,
-        None => {
-            let mut response = Response::new(Body::from("not found\n"));
-            *response.status_mut() = hyper::StatusCode::NOT_FOUND;
-            return Ok(response);
+    let route = match route {
+        Some(route) => Some(route),
+        // A path routed for other methods only gets the server-wide policy.
+        None if path_routed && let Some(cors) = &context.cors => {
+            return Ok(cors.preflight(&request));
         }
+        None if preflight => {
+            return Ok(plain_response(hyper::StatusCode::NOT_FOUND, "not found\n"));
+        }
+        // Unrouted paths are answered with 404 through the layers of their prefixes.
+        None => None,
     };
 
     #[cfg(feature = "bench")]
-    match handler_id {
-        BUILTIN_PLAINTEXT => {
-            let mut response = Response::new(Body::from("Hello, World!\n"));
-            *response.status_mut() = hyper::StatusCode::OK;
-            return Ok(response);
+    match route.as_ref().map(|route| route.handler_id) {
+        Some(BUILTIN_PLAINTEXT) => {
+            return Ok(plain_response(hyper::StatusCode::OK, "Hello, World!\n"));
         }
-        BUILTIN_NOOP => {
+        Some(BUILTIN_NOOP) => {
             let mut response = Response::new(Body::empty());
             *response.status_mut() = hyper::StatusCode::NO_CONTENT;
             return Ok(response);
@@ -68,39 +213,399 @@ async fn handle(
         _ => {}
     }
 
-    let body_bytes = hyper::body::to_bytes(request.into_body()).await?;
-    let request_frame = frames::encode_request(method, &path, &body_bytes);
+    let cors = route
+        .as_ref()
+        .and_then(|route| route.options.cors.clone())
+        .or_else(|| context.cors.clone());
+    if preflight {
+        return Ok(match &cors {
+            Some(cors) => cors.preflight(&request),
+            None => plain_response(hyper::StatusCode::NOT_FOUND, "not found\n"),
+        });
+    }
+
+    let origin = request.headers().get(hyper::header::ORIGIN).cloned();
+    let client = forwarded::resolve(&request, &connection, &context.trusted_proxies);
+
+    // Layers run around everything the route does, so that a layer answering the request skips it all.
+    let mut layers = prefix_layers;
+    layers.extend(
+        route
+            .iter()
+            .flat_map(|route| route.options.layers.iter().cloned()),
+    );
+    let layer_context = LayerContext {
+        client: &client,
+        received,
+    };
+    let (mut response, ran) = match layers::run_before(&layers, &request, &layer_context) {
+        Some(answered) => answered,
+        None => {
+            let response = match route {
+                Some(route) => {
+                    handle_route(request, request_id, &context, &connection, &client, route).await?
+                }
+                None => plain_response(hyper::StatusCode::NOT_FOUND, "not found\n"),
+            };
+            (response, layers.len())
+        }
+    };
+    layers::run_after(&layers[..ran], &mut response, &layer_context);
+
+    if let (Some(cors), Some(origin)) = (&cors, &origin) {
+        cors.apply(origin, &mut response);
+    }
+    return Ok(response);
+}
+
+/// The route a request matched, or the static mount serving it.
+struct MatchedRoute {
+    handler_id: HandlerId,
+    options: RouteOptions,
+    mount: Option<Arc<StaticMount>>,
+}
+
+/// Handles a request matching `route`, from its rate limit to the response of its handler.
+async fn handle_route(
+    request: Request<Body>,
+    request_id: &RequestId,
+    context: &ServerContext,
+    connection: &ConnectionInfo,
+    client: &ClientInfo,
+    route: MatchedRoute,
+) -> Result<Response<Body>, hyper::Error> {
+    let MatchedRoute {
+        handler_id,
+        options,
+        mount,
+    } = route;
+    let method = router::method_to_u8(request.method());
+    let version = router::version_to_u8(request.version());
+    let path = request.uri().path().to_string();
+
+    let rate_limiter = context
+        .route_rate_limiters
+        .get(&handler_id)
+        .or(context.rate_limiter.as_ref());
+    let rate_limit = rate_limiter.map(|limiter| limiter.check(&request, client, handler_id));
+    if let Some(rate_limit) = &rate_limit
+        && !rate_limit.allowed
+    {
+        ServerStats::increment(&context.stats.rejected_rate_limited);
+        let mut response =
+            plain_response(hyper::StatusCode::TOO_MANY_REQUESTS, "too many requests\n");
+        rate_limit.apply(&mut response);
+        return Ok(response);
+    }
+
+    // Static files are served by Rust, without waiting on Swift handlers.
+    if let Some(mount) = mount {
+        let mut response = static_files::serve(&mount, &request).await;
+        if let Some(rate_limit) = &rate_limit {
+            rate_limit.apply(&mut response);
+        }
+        return Ok(response);
+    }
+
+    // Read before the request is consumed by its body, to shape the response for the client.
+    let negotiation = Negotiation::from_request(&request, &options);
+
+    // Cache hits are served without dispatching to Swift, so they are not shed.
+    let cache = context.cache.as_ref().filter(|_| options.cache);
+    let cache_key = cache.and_then(|cache| cache.key(&request, client));
+    if let (Some(cache), Some(key)) = (cache, &cache_key) {
+        if let Some(cached) = cache.get(key) {
+            ServerStats::increment(&context.stats.cache_hits);
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = cached.status;
+            *response.headers_mut() = cached.headers;
+            return Ok(finish_response(
+                response,
+                cached.body,
+                &negotiation,
+                &options,
+                context,
+                rate_limit.as_ref(),
+            ));
+        }
+        ServerStats::increment(&context.stats.cache_misses);
+    }
+
+    if context.load_shedder.should_shed(&context.stats) {
+        ServerStats::increment(&context.stats.rejected_shed);
+        return Ok(unavailable_response(context));
+    }
+
+    // Encoded bodies are rejected before being read when they cannot be decoded.
+    let content_codings = match &context.decompression {
+        Some(_) => match request.headers().get(hyper::header::CONTENT_ENCODING) {
+            Some(value) => match value.to_str().ok().map(compression::content_codings) {
+                Some(Ok(codings)) => codings,
+                _ => return Ok(unsupported_encoding_response()),
+            },
+            None => Vec::new(),
+        },
+        None => Vec::new(),
+    };
+
+    let max_body_size = options
+        .max_body_size
+        .unwrap_or(context.limits.max_body_size);
+    let body = body::read_limited(request.into_body(), max_body_size);
+    let body = match context.limits.body_read_timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, body).await {
+            Ok(body) => body,
+            Err(_) => {
+                #[cfg(feature = "debug")]
+                eprintln!("[Rust] request {request_id}: body read timed out");
+                return Ok(plain_response(
+                    hyper::StatusCode::REQUEST_TIMEOUT,
+                    "request timeout\n",
+                ));
+            }
+        },
+        None => body.await,
+    };
+    let mut body_bytes = match body {
+        Ok(bytes) => bytes,
+        Err(BodyError::TooLarge) => {
+            ServerStats::increment(&context.stats.rejected_body_too_large);
+            return Ok(plain_response(
+                hyper::StatusCode::PAYLOAD_TOO_LARGE,
+                "payload too large\n",
+            ));
+        }
+        Err(BodyError::Read(e)) => return Err(e),
+    };
+
+    // Codings are listed in the order they were applied, so they are removed from the last one.
+    for coding in content_codings.iter().rev() {
+        let max_size = context
+            .decompression
+            .as_ref()
+            .and_then(|decompression| decompression.max_size)
+            .unwrap_or(max_body_size);
+        body_bytes = match coding.decompress(&body_bytes, max_size) {
+            Ok(decoded) => Bytes::from(decoded),
+            Err(DecompressError::TooLarge) => {
+                ServerStats::increment(&context.stats.rejected_body_too_large);
+                return Ok(plain_response(
+                    hyper::StatusCode::PAYLOAD_TOO_LARGE,
+                    "payload too large\n",
+                ));
+            }
+            Err(DecompressError::Invalid) => {
+                return Ok(plain_response(
+                    hyper::StatusCode::BAD_REQUEST,
+                    "invalid request body encoding\n",
+                ));
+            }
+        };
+    }
+
+    let request_frame = frames::encode_request(
+        method,
+        &path,
+        &body_bytes,
+        version,
+        connection,
+        client,
+        request_id,
+    );
 
+    let Some(_permit) = context.dispatch.admit(&context.stats).await else {
+        ServerStats::increment(&context.stats.rejected_overloaded);
+        return Ok(unavailable_response(context));
+    };
+
+    let dispatched = Instant::now();
     let response_frame = match dispatch::dispatch_to_swift(handler_id, &request_frame).await {
-        Ok(b) => b,
+        Ok(b) => {
+            context
+                .load_shedder
+                .record(dispatched.elapsed(), &context.stats);
+            b
+        }
         Err(dispatch::DispatchErr::Timeout) => {
-            let mut response = Response::new(Body::from("timeout\n"));
-            *response.status_mut() = hyper::StatusCode::GATEWAY_TIMEOUT;
-            return Ok(response);
+            context
+                .load_shedder
+                .record(dispatched.elapsed(), &context.stats);
+            #[cfg(feature = "debug")]
+            eprintln!("[Rust] request {request_id}: handler {handler_id} timed out");
+            return Ok(plain_response(
+                hyper::StatusCode::GATEWAY_TIMEOUT,
+                "timeout\n",
+            ));
         }
         Err(_) => {
-            let mut response = Response::new(Body::from("swift dispatch failed\n"));
-            *response.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
-            return Ok(response);
+            #[cfg(feature = "debug")]
+            eprintln!("[Rust] request {request_id}: handler {handler_id} dropped its completion");
+            return Ok(plain_response(
+                hyper::StatusCode::INTERNAL_SERVER_ERROR,
+                "swift dispatch failed\n",
+            ));
         }
     };
 
-    let (status, body) = match frames::decode_response(&response_frame) {
+    let (status, headers, body) = match frames::decode_response(&response_frame) {
         Some(v) => v,
         None => {
-            let mut response = Response::new(Body::from("invalid response frame\n"));
-            *response.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
-            return Ok(response);
+            #[cfg(feature = "debug")]
+            eprintln!(
+                "[Rust] request {request_id}: handler {handler_id} returned an invalid response frame"
+            );
+            return Ok(plain_response(
+                hyper::StatusCode::INTERNAL_SERVER_ERROR,
+                "invalid response frame\n",
+            ));
         }
     };
 
-    let mut response = Response::new(Body::from(body));
-    *response.status_mut() =
+    let status =
         hyper::StatusCode::from_u16(status).unwrap_or(hyper::StatusCode::INTERNAL_SERVER_ERROR);
-    return Ok(response);
+    if let (Some(cache), Some(key)) = (cache, cache_key) {
+        cache.insert(key, status, &headers, &body);
+    }
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+
+    return Ok(finish_response(
+        response,
+        body,
+        &negotiation,
+        &options,
+        context,
+        rate_limit.as_ref(),
+    ));
+}
+
+/// What shapes the response of a handler for the client, read from the request before its body is consumed.
+struct Negotiation {
+    accept_encoding: Option<String>,
+    range_headers: Option<RangeHeaders>,
+    conditional_headers: Option<ConditionalHeaders>,
+}
+
+impl Negotiation {
+    fn from_request(request: &Request<Body>, options: &RouteOptions) -> Negotiation {
+        Negotiation {
+            accept_encoding: request
+                .headers()
+                .get(hyper::header::ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string()),
+            range_headers: RangeHeaders::from_request(request),
+            conditional_headers: options
+                .etag
+                .then(|| ConditionalHeaders::from_request(request)),
+        }
+    }
+}
+
+/// Applies the validators, ranges and encodings negotiated by the client to the response of a handler,
+/// fresh or cached, and sets its `body`.
+fn finish_response(
+    mut response: Response<Body>,
+    body: Vec<u8>,
+    negotiation: &Negotiation,
+    options: &RouteOptions,
+    context: &ServerContext,
+    rate_limit: Option<&RateLimitDecision>,
+) -> Response<Body> {
+    let body = match &negotiation.conditional_headers {
+        Some(conditional_headers) => conditional_headers.apply(&mut response, body),
+        None => body,
+    };
+
+    // Ranges apply to the identity representation, and partial responses are never compressed.
+    let body = match &negotiation.range_headers {
+        Some(range_headers) => range_headers.apply(&mut response, body),
+        None => body,
+    };
+
+    match &context.compression {
+        Some(compression) if !options.skip_compression => compression::apply(
+            &mut response,
+            body,
+            negotiation.accept_encoding.as_deref(),
+            compression,
+        ),
+        _ => *response.body_mut() = Body::from(body),
+    }
+
+    if let Some(rate_limit) = rate_limit {
+        rate_limit.apply(&mut response);
+    }
+    return response;
+}
+
+/// Returns the error response of a request whose target or headers exceed the limits, counting the rejection.
+fn check_head_limits(request: &Request<Body>, context: &ServerContext) -> Option<Response<Body>> {
+    let limits = &context.limits;
+
+    let uri_length = request
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str().len())
+        .unwrap_or(0);
+    if uri_length > limits.max_uri_length {
+        ServerStats::increment(&context.stats.rejected_uri_too_long);
+        return Some(plain_response(
+            hyper::StatusCode::URI_TOO_LONG,
+            "uri too long\n",
+        ));
+    }
+
+    let headers = request.headers();
+    let header_bytes: usize = headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len())
+        .sum();
+    if headers.len() > limits.max_header_count || header_bytes > limits.max_header_bytes {
+        ServerStats::increment(&context.stats.rejected_headers_too_large);
+        return Some(plain_response(
+            hyper::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            "request header fields too large\n",
+        ));
+    }
+
+    return None;
 }
 
-pub fn start_server(port: Port, routes: SharedRoutes) -> *mut ServerHandle {
+/// The 415 response of a request body whose encoding cannot be decoded, listing the supported ones.
+fn unsupported_encoding_response() -> Response<Body> {
+    let mut response = plain_response(
+        hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "unsupported content encoding\n",
+    );
+    response.headers_mut().insert(
+        hyper::header::ACCEPT_ENCODING,
+        hyper::header::HeaderValue::from_static("gzip, deflate, br, zstd"),
+    );
+    return response;
+}
+
+/// The 503 response of a request rejected to protect the ones already admitted.
+fn unavailable_response(context: &ServerContext) -> Response<Body> {
+    let mut response = plain_response(hyper::StatusCode::SERVICE_UNAVAILABLE, "server busy\n");
+    response
+        .headers_mut()
+        .insert(hyper::header::RETRY_AFTER, context.retry_after.into());
+    return response;
+}
+
+fn plain_response(status: hyper::StatusCode, body: &'static str) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    return response;
+}
+
+pub fn start_server(
+    config: ServerConfig,
+    routes: SharedRoutes,
+) -> Result<ServerHandle, StartupError> {
     #[cfg(feature = "debug")]
     println!("[Rust] starting server");
 
@@ -110,94 +615,499 @@ pub fn start_server(port: Port, routes: SharedRoutes) -> *mut ServerHandle {
     let (shutdown_transmitter, shutdown_receiver) = oneshot::channel::<()>();
 
     let routes_for_thread = routes.clone();
+    let stats = Arc::new(ServerStats::default());
+    let stats_for_thread = stats.clone();
+
+    let (ready_transmitter, ready_receiver) = mpsc::channel::<Result<Started, StartupError>>();
 
-    let (ready_transmitter, ready_receiver) = mpsc::channel::<Result<(), String>>();
+    let mut thread_builder = thread::Builder::new();
+    if let Some(prefix) = &config.runtime.thread_name_prefix {
+        thread_builder = thread_builder.name(format!("{}-server", prefix));
+    }
 
     // We spawn a new thread for Tokio to run on to create a clear lifetime boundary.
     // Returns the handler needed to wait for the thread to finish its work (join).
-    let join_handle = thread::spawn(move || {
+    let join_handle = thread_builder.spawn(move || {
         // Pass the receiver to the run_server function to await any shutdown request from the transmitter.
         run_server(
-            port,
+            config,
             shutdown_receiver,
             routes_for_thread,
+            stats_for_thread,
             ready_transmitter,
         );
     });
 
+    let join_handle = match join_handle {
+        Ok(join_handle) => join_handle,
+        Err(e) => {
+            return Err(StartupError::new(KiriError::new(
+                KiriStatus::RuntimeFailed,
+                format!("Failed to start server: spawn server thread failed: {}", e),
+            )));
+        }
+    };
+
     match ready_receiver.recv() {
-        Ok(Ok(())) => {
+        Ok(Ok(started)) => {
             // Return a server handle to the client so that it can:
             // - transmit the shutdown request (shutdown_transmitter)
             // - and await for the operation to finish (join)
-            let handle = ServerHandle {
+            Ok(ServerHandle {
                 shutdown_transmitter: Some(shutdown_transmitter),
                 join: Some(join_handle),
                 routes,
-            };
-
-            Box::into_raw(Box::new(handle))
+                bound_addresses: started.bound_addresses,
+                stats,
+                #[cfg(feature = "tls")]
+                tls: started.tls,
+            })
         }
-        Ok(Err(message)) => {
-            set_last_error(format!("Failed to start server: {}", message));
+        Ok(Err(error)) => {
             let _ = join_handle.join();
-            std::ptr::null_mut()
+            Err(StartupError {
+                error: KiriError::new(
+                    error.error.status,
+                    format!("Failed to start server: {}", error.error.message),
+                ),
+                listener_errors: error.listener_errors,
+            })
         }
         Err(_) => {
-            set_last_error(
-                "Failed to start server: internal error (startup channel closed)".to_string(),
-            );
             let _ = join_handle.join();
-            std::ptr::null_mut()
+            Err(StartupError::new(KiriError::new(
+                KiriStatus::Internal,
+                "Failed to start server: internal error (startup channel closed)",
+            )))
         }
     }
 }
 
 pub fn run_server(
-    port: Port,
+    config: ServerConfig,
     shutdown_receiver: oneshot::Receiver<()>,
     routes: SharedRoutes,
-    ready_transmitter: mpsc::Sender<Result<(), String>>,
+    stats: Arc<ServerStats>,
+    ready_transmitter: mpsc::Sender<Result<Started, StartupError>>,
 ) {
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .expect("Failed toclear build Tokio runtime");
+    let runtime = match build_runtime(&config.runtime) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            let _ = ready_transmitter.send(Err(StartupError::new(KiriError::new(
+                KiriStatus::RuntimeFailed,
+                format!("build Tokio runtime failed: {}", e),
+            ))));
+            return;
+        }
+    };
 
     runtime.block_on(async move {
-        let bind_ip = std::env::var("KIRI_BIND_HOST")
-            .ok()
-            .and_then(|value| value.parse::<IpAddr>().ok())
-            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
-        let address = SocketAddr::new(bind_ip, port);
-
-        let builder = match Server::try_bind(&address) {
-            Ok(builder) => {
-                let _ = ready_transmitter.send(Ok(()));
-                builder
-            }
-            Err(e) => {
-                let _ = ready_transmitter.send(Err(format!("bind {} failed: {}", address, e)));
-                return;
-            }
-        };
+        if config.listeners.is_empty() {
+            let _ = ready_transmitter.send(Err(StartupError::new(KiriError::new(
+                KiriStatus::InvalidArgument,
+                "no listeners configured",
+            ))));
+            return;
+        }
 
-        let make_service = make_service_fn(move |_connection| {
-            let routes = routes.clone();
-            async move {
-                Ok::<_, hyper::Error>(service_fn(move |request| handle(request, routes.clone())))
+        // Bind every listener before serving any, so that a failure leaves no listener behind.
+        let mut listeners = Vec::with_capacity(config.listeners.len());
+        let mut listener_errors = Vec::new();
+        for (index, listener_config) in config.listeners.iter().enumerate() {
+            match Listener::bind(listener_config, config.http2) {
+                Ok(listener) => listeners.push(listener),
+                Err(error) => listener_errors.push(ListenerError { index, error }),
             }
-        });
+        }
+
+        if !listener_errors.is_empty() {
+            let message = listener_errors
+                .iter()
+                .map(|e| format!("listener {}: {}", e.index, e.error.message))
+                .collect::<Vec<_>>()
+                .join("; ");
+            let _ = ready_transmitter.send(Err(StartupError {
+                error: KiriError::new(listener_errors[0].error.status, message),
+                listener_errors,
+            }));
+            return;
+        }
 
-        let server = builder.serve(make_service);
-        let graceful = server.with_graceful_shutdown(async move {
-            // When the shutdown request is received, shut the server down gracefully.
-            let _ = shutdown_receiver.await;
+        // Report the addresses resolved by the OS, which differ from the requested ones when binding port 0.
+        let _ = ready_transmitter.send(Ok(Started {
+            bound_addresses: listeners
+                .iter()
+                .map(|l| l.local_address().clone())
+                .collect(),
+            #[cfg(feature = "tls")]
+            tls: listeners.iter().filter_map(|l| l.tls().cloned()).collect(),
+        }));
+
+        // With HTTP/2 enabled, hyper serves HTTP/1 and detects the h2 preface of prior-knowledge clients.
+        let mut http = Http::new();
+        if !config.http2 {
+            http.http1_only(true);
+        }
+        // HTTP/2 counts 32 bytes of overhead per field and the pseudo-headers, so leave it twice the room
+        // to decode header blocks near the limits and reject them with a counted 431. Larger blocks are still
+        // refused by HTTP/2 itself, without being counted.
+        // Saturates, as both limits are set over FFI to any value.
+        let max_header_list_size = config
+            .limits
+            .max_header_count
+            .saturating_mul(32)
+            .saturating_add(config.limits.max_header_bytes)
+            .saturating_mul(2);
+        http.http2_max_header_list_size(u32::try_from(max_header_list_size).unwrap_or(u32::MAX));
+        let (stop_transmitter, stop_receiver) = watch::channel(false);
+        let (drain_transmitter, mut drain_receiver) = tokio_mpsc::channel::<()>(1);
+
+        // The routes are frozen once the server starts, so their rate limiters are created once.
+        let route_rate_limiters = routes
+            .read()
+            .await
+            .routes
+            .iter()
+            .filter_map(|route| {
+                let limit = route.options.rate_limit.clone()?;
+                Some((route.handler_id, RateLimiter::new(limit)))
+            })
+            .collect();
+
+        // All listeners share the same route table, limits, and runtime.
+        let context = Arc::new(ServerContext {
+            routes,
+            limits: config.limits.clone(),
+            connections: config.connections.clone(),
+            connection_slots: config
+                .connections
+                .max_connections
+                .map(|max| Arc::new(Semaphore::new(max))),
+            dispatch: DispatchGate::new(&config.dispatch),
+            load_shedder: LoadShedder::new(&config.load_shedding),
+            retry_after: config.load_shedding.retry_after.as_secs_f64().ceil() as u64,
+            rate_limiter: config.rate_limit.clone().map(RateLimiter::new),
+            route_rate_limiters,
+            trusted_proxies: config.trusted_proxies.clone(),
+            compression: config.compression.clone(),
+            decompression: config.decompression.clone(),
+            cache: config.cache.clone().map(Cache::new),
+            cors: config.cors.clone().map(Arc::new),
+            request_ids: config.request_ids.clone(),
+            request_id_generator: RequestIdGenerator::default(),
+            next_connection_id: AtomicU64::new(1),
+            stats,
         });
+        let accept_loops: Vec<_> = listeners
+            .into_iter()
+            .map(|listener| {
+                tokio::spawn(listener::serve(
+                    listener,
+                    context.clone(),
+                    http.clone(),
+                    stop_receiver.clone(),
+                    drain_transmitter.clone(),
+                ))
+            })
+            .collect();
 
-        if let Err(e) = graceful.await {
-            #[cfg(feature = "debug")]
-            eprintln!("[Rust] server error: {e}");
+        // When the shutdown request is received, shut the server down gracefully.
+        let _ = shutdown_receiver.await;
+        let _ = stop_transmitter.send(true);
+
+        for accept_loop in accept_loops {
+            let _ = accept_loop.await;
         }
+
+        // Wait for the open connections to finish their in-flight requests.
+        drop(drain_transmitter);
+        let _ = drain_receiver.recv().await;
     })
 }
+
+fn build_runtime(config: &RuntimeConfig) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = match config.current_thread {
+        true => tokio::runtime::Builder::new_current_thread(),
+        false => tokio::runtime::Builder::new_multi_thread(),
+    };
+
+    builder.enable_all();
+
+    if let Some(worker_threads) = config.worker_threads {
+        builder.worker_threads(worker_threads);
+    }
+
+    if let Some(max_blocking_threads) = config.max_blocking_threads {
+        builder.max_blocking_threads(max_blocking_threads);
+    }
+
+    if let Some(prefix) = config.thread_name_prefix.clone() {
+        let next_index = AtomicUsize::new(0);
+        builder.thread_name_fn(move || {
+            let index = next_index.fetch_add(1, Ordering::Relaxed);
+            format!("{}-{}", prefix, index)
+        });
+    }
+
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        core::router,
+        ffi_c::{
+            config_handle::kiri_server_config_set_cors,
+            router_handle::{
+                kiri_router_add_layer, kiri_router_mount_directory, kiri_router_register_route,
+                kiri_router_set_route_cors,
+            },
+        },
+        status::KiriStatus,
+        testing::{TestServer, wait_for_dispatch, wait_until},
+    };
+
+    #[test]
+    fn resetting_an_http2_stream_cancels_its_dispatch() {
+        let server = TestServer::start(
+            |router| {
+                let pattern = "/held";
+                kiri_router_register_route(router, 0, pattern.as_ptr(), pattern.len(), 1_000);
+            },
+            |_| {},
+        );
+
+        let runtime = tokio::runtime::Runtime::new().expect("runtime");
+        runtime.block_on(async {
+            let stream = tokio::net::TcpStream::connect(("127.0.0.1", server.port))
+                .await
+                .expect("connect");
+            let (mut client, connection) = h2::client::handshake(stream).await.expect("handshake");
+            tokio::spawn(connection);
+
+            let request = hyper::Request::get("http://localhost/held")
+                .body(())
+                .expect("request");
+            let (_response, mut stream) = client.send_request(request, true).expect("send");
+
+            let dispatched = tokio::task::spawn_blocking(|| wait_for_dispatch(1_000))
+                .await
+                .expect("dispatch");
+            assert!(!dispatched.is_cancelled());
+
+            stream.send_reset(h2::Reason::CANCEL);
+            // Well before the dispatch would time out on its own.
+            tokio::task::spawn_blocking(move || {
+                let within = Duration::from_secs(1);
+                wait_until("the dispatch is cancelled", within, || {
+                    dispatched.is_cancelled()
+                });
+            })
+            .await
+            .expect("cancellation");
+        });
+    }
+
+    #[test]
+    fn preflights_get_the_policy_of_the_route_of_their_method() {
+        let server = TestServer::start(
+            |router| {
+                let pattern = "/x";
+                // Only GET is routable: other methods share one method code.
+                for (method, handler_id, origin, methods) in [
+                    (0, 1, "https://get.example", "GET"),
+                    (router::UNSUPPORTED_METHOD, 2, "https://put.example", "PUT"),
+                ] {
+                    kiri_router_register_route(
+                        router,
+                        method,
+                        pattern.as_ptr(),
+                        pattern.len(),
+                        handler_id,
+                    );
+                    let status = kiri_router_set_route_cors(
+                        router,
+                        handler_id,
+                        origin.as_ptr(),
+                        origin.len(),
+                        methods.as_ptr(),
+                        methods.len(),
+                        std::ptr::null(),
+                        0,
+                        false,
+                        0,
+                    );
+                    assert_eq!(status, KiriStatus::Ok);
+                }
+
+                let pattern = "/y";
+                kiri_router_register_route(router, 0, pattern.as_ptr(), pattern.len(), 3);
+            },
+            |config| {
+                let origin = "https://global.example";
+                let status = kiri_server_config_set_cors(
+                    config,
+                    origin.as_ptr(),
+                    origin.len(),
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null(),
+                    0,
+                    false,
+                    0,
+                );
+                assert_eq!(status, KiriStatus::Ok);
+            },
+        );
+
+        let preflight = |path: &str, origin: &str, method: &str| {
+            let headers = [
+                ("origin", origin),
+                ("access-control-request-method", method),
+            ];
+            return server.request("OPTIONS", path, &headers).status;
+        };
+
+        assert_eq!(preflight("/x", "https://get.example", "GET"), 204);
+        assert_eq!(preflight("/x", "https://put.example", "GET"), 403);
+        assert_eq!(preflight("/x", "https://put.example", "PUT"), 204);
+        assert_eq!(preflight("/x", "https://get.example", "PUT"), 403);
+
+        // Without a route for the method, the server-wide policy applies.
+        assert_eq!(preflight("/y", "https://global.example", "POST"), 204);
+        assert_eq!(preflight("/z", "https://global.example", "GET"), 404);
+
+        let response = server.get("/x", &[("origin", "https://get.example")]);
+        assert_eq!(response.body, b"1");
+        assert_eq!(
+            response.header("access-control-allow-origin"),
+            Some("https://get.example")
+        );
+    }
+
+    fn add_api_key_layer(router: *const std::ffi::c_void, prefix: &str) {
+        let (name, config) = ("api_key", "keys=secret");
+        let status = kiri_router_add_layer(
+            router,
+            prefix.as_ptr(),
+            prefix.len(),
+            name.as_ptr(),
+            name.len(),
+            config.as_ptr(),
+            config.len(),
+        );
+        assert_eq!(status, KiriStatus::Ok);
+    }
+
+    #[test]
+    fn prefix_layers_apply_to_the_paths_under_them_whatever_route_matches() {
+        let server = TestServer::start(
+            |router| {
+                let pattern = "/:id";
+                kiri_router_register_route(router, 0, pattern.as_ptr(), pattern.len(), 1);
+                add_api_key_layer(router, "/admin");
+            },
+            |_| {},
+        );
+
+        assert_eq!(server.get("/admin", &[]).status, 401);
+        assert_eq!(server.get("/%61dmin", &[]).status, 401);
+        assert_eq!(server.get("/admin", &[("x-api-key", "secret")]).status, 200);
+        assert_eq!(server.get("/other", &[]).status, 200);
+    }
+
+    #[test]
+    fn prefix_layers_apply_to_the_paths_under_them_within_a_mount() {
+        let root = std::env::temp_dir().join(format!("kiri-prefix-layers-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("private")).expect("create directory");
+        std::fs::write(root.join("private/secret.txt"), "secret").expect("write file");
+        std::fs::write(root.join("public.txt"), "public").expect("write file");
+
+        let server = TestServer::start(
+            |router| {
+                let (prefix, root) = ("/static", root.to_str().expect("UTF-8 path"));
+                let status = kiri_router_mount_directory(
+                    router,
+                    prefix.as_ptr(),
+                    prefix.len(),
+                    root.as_ptr(),
+                    root.len(),
+                    std::ptr::null(),
+                    0,
+                    1,
+                );
+                assert_eq!(status, KiriStatus::Ok);
+                add_api_key_layer(router, "/static/private");
+            },
+            |_| {},
+        );
+
+        let private = server.get("/static/private/secret.txt", &[]);
+        let encoded = server.get("/static/%70rivate/secret.txt", &[]);
+        let public = server.get("/static/public.txt", &[]);
+        std::fs::remove_dir_all(&root).expect("remove directory");
+
+        assert_eq!(private.status, 401);
+        assert_eq!(encoded.status, 401);
+        assert_eq!(public.status, 200);
+        assert_eq!(public.body, b"public");
+    }
+
+    #[test]
+    fn prefix_layers_apply_to_unrouted_paths_under_them() {
+        let server = TestServer::start(
+            |router| {
+                let pattern = "/api/items";
+                kiri_router_register_route(router, 0, pattern.as_ptr(), pattern.len(), 1);
+                add_api_key_layer(router, "/api");
+
+                let (prefix, name) = ("/", "server_timing");
+                let status = kiri_router_add_layer(
+                    router,
+                    prefix.as_ptr(),
+                    prefix.len(),
+                    name.as_ptr(),
+                    name.len(),
+                    std::ptr::null(),
+                    0,
+                );
+                assert_eq!(status, KiriStatus::Ok);
+            },
+            |config| {
+                let origin = "https://app.example";
+                let status = kiri_server_config_set_cors(
+                    config,
+                    origin.as_ptr(),
+                    origin.len(),
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null(),
+                    0,
+                    false,
+                    0,
+                );
+                assert_eq!(status, KiriStatus::Ok);
+            },
+        );
+
+        assert_eq!(server.get("/api/missing", &[]).status, 401);
+
+        let key = ("x-api-key", "secret");
+        let missing = server.get("/api/missing", &[key, ("origin", "https://app.example")]);
+        assert_eq!(missing.status, 404);
+        assert!(missing.header("server-timing").is_some());
+        assert_eq!(
+            missing.header("access-control-allow-origin"),
+            Some("https://app.example")
+        );
+        assert!(missing.header("x-request-id").is_some());
+
+        let elsewhere = server.get("/elsewhere", &[]);
+        assert_eq!(elsewhere.status, 404);
+        assert!(elsewhere.header("server-timing").is_some());
+
+        assert_eq!(server.get("/api/items", &[key]).status, 200);
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use hyper::{
+    Body, Method, Request, Response, StatusCode,
+    body::Bytes,
+    header::{
+        ACCEPT_RANGES, ALLOW, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, HeaderValue,
+        IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, LOCATION, RANGE,
+    },
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::core::{
+    etag,
+    range::{self, RangeRequest},
+};
+
+/// The size of the chunks files are streamed in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A directory served by Rust under a path prefix, without dispatching to Swift.
+#[derive(Debug)]
+pub struct StaticMount {
+    /// The path prefix, without leading or trailing slashes.
+    pub prefix: String,
+    pub root: PathBuf,
+    /// The files served for a request to a directory, in order of preference.
+    pub index_files: Vec<String>,
+}
+
+impl StaticMount {
+    pub const DEFAULT_INDEX_FILES: [&str; 1] = ["index.html"];
+
+    pub fn new(prefix: &str, root: PathBuf, index_files: Vec<String>) -> StaticMount {
+        StaticMount {
+            prefix: prefix.trim_matches('/').to_string(),
+            root,
+            index_files,
+        }
+    }
+
+    /// Whether `path` is the prefix of the mount, or below it.
+    pub fn matches(&self, path: &str) -> bool {
+        return self.relative_path(path).is_some();
+    }
+
+    /// The part of `path` below the prefix, segment-wise, e.g. `css/site.css` for `/assets/css/site.css`.
+    fn relative_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let path = path.trim_start_matches('/');
+        if self.prefix.is_empty() {
+            return Some(path);
+        }
+
+        let rest = path.strip_prefix(self.prefix.as_str())?;
+        return match rest.is_empty() {
+            true => Some(rest),
+            false => rest.strip_prefix('/'),
+        };
+    }
+}
+
+/// Serves the file targeted by `request` from `mount`, answering GET and HEAD requests only.
+pub async fn serve(mount: &StaticMount, request: &Request<Body>) -> Response<Body> {
+    let head = request.method() == Method::HEAD;
+    if request.method() != Method::GET && !head {
+        let mut response = empty_response(StatusCode::METHOD_NOT_ALLOWED);
+        response
+            .headers_mut()
+            .insert(ALLOW, HeaderValue::from_static("GET, HEAD"));
+        return response;
+    }
+
+    let Some(relative) = mount.relative_path(request.uri().path()) else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+    let Some(mut path) = resolve(&mount.root, relative) else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+
+    let Ok(mut metadata) = tokio::fs::metadata(&path).await else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+
+    if metadata.is_dir() {
+        // Relative links of an index file resolve against the directory only with a trailing slash.
+        if !request.uri().path().ends_with('/') {
+            let location = match request.uri().query() {
+                Some(query) => format!("{}/?{}", request.uri().path(), query),
+                None => format!("{}/", request.uri().path()),
+            };
+            let mut response = empty_response(StatusCode::MOVED_PERMANENTLY);
+            if let Ok(location) = HeaderValue::from_str(&location) {
+                response.headers_mut().insert(LOCATION, location);
+            }
+            return response;
+        }
+
+        let mut index = None;
+        for name in &mount.index_files {
+            let candidate = path.join(name);
+            if let Ok(candidate_metadata) = tokio::fs::metadata(&candidate).await
+                && candidate_metadata.is_file()
+            {
+                index = Some((candidate, candidate_metadata));
+                break;
+            }
+        }
+
+        let Some((index_path, index_metadata)) = index else {
+            return empty_response(StatusCode::NOT_FOUND);
+        };
+        path = index_path;
+        metadata = index_metadata;
+    }
+
+    if !metadata.is_file() {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
+
+    // The file is checked once opened, so that a symbolic link swapped in after the check cannot escape the root.
+    let Ok(mut file) = tokio::fs::File::open(&path).await else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+    if !is_within_root(&mount.root, &file).await {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
+    let Ok(metadata) = file.metadata().await else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+    if !metadata.is_file() {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok().map(range::truncate_to_seconds);
+    let etag = entity_tag(len, metadata.modified().ok());
+    let headers = request.headers();
+
+    if is_not_modified(request, &etag, modified) {
+        let mut response = empty_response(StatusCode::NOT_MODIFIED);
+        set_validators(&mut response, &etag, modified);
+        return response;
+    }
+
+    // A stale `If-Range` means the client's partial copy is outdated: the full file is sent instead.
+    let range_applies = headers
+        .get(IF_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|if_range| range::if_range_matches(if_range, Some(&etag), modified));
+    let range_request = match headers.get(RANGE).and_then(|value| value.to_str().ok()) {
+        Some(range_header) if range_applies => range::parse(range_header, len),
+        _ => RangeRequest::Full,
+    };
+
+    let (status, start, count) = match range_request {
+        // Multiple ranges are not worth a multipart body for a file: the full file is sent instead.
+        RangeRequest::Ranges(ranges) if ranges.len() == 1 => {
+            let range = &ranges[0];
+            (
+                StatusCode::PARTIAL_CONTENT,
+                *range.start(),
+                range.end() - range.start() + 1,
+            )
+        }
+        RangeRequest::Unsatisfiable => {
+            let mut response = empty_response(StatusCode::RANGE_NOT_SATISFIABLE);
+            if let Ok(content_range) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                response.headers_mut().insert(CONTENT_RANGE, content_range);
+            }
+            return response;
+        }
+        _ => (StatusCode::OK, 0, len),
+    };
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    set_validators(&mut response, &etag, modified);
+    let response_headers = response.headers_mut();
+    response_headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type(&path)));
+    response_headers.insert(CONTENT_LENGTH, HeaderValue::from(count));
+    if status == StatusCode::PARTIAL_CONTENT
+        && let Ok(content_range) =
+            HeaderValue::from_str(&range::content_range(&(start..=start + count - 1), len))
+    {
+        response_headers.insert(CONTENT_RANGE, content_range);
+    }
+
+    if head || count == 0 {
+        return response;
+    }
+
+    if start > 0 && file.seek(SeekFrom::Start(start)).await.is_err() {
+        return empty_response(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // The file is streamed by its own task, so that a large file is never held in memory.
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut remaining = count;
+        while remaining > 0 {
+            let mut chunk = vec![0u8; CHUNK_SIZE.min(remaining as usize)];
+            match file.read(&mut chunk).await {
+                Ok(0) | Err(_) => {
+                    // The file shrank or failed: the client sees a truncated body rather than a wrong one.
+                    sender.abort();
+                    return;
+                }
+                Ok(read) => {
+                    chunk.truncate(read);
+                    remaining -= read as u64;
+                    if sender.send_data(Bytes::from(chunk)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    *response.body_mut() = body;
+
+    return response;
+}
+
+/// Maps the percent-encoded `relative` path onto `root`. Returns `None` for traversal attempts,
+/// hidden files, and segments that do not decode to a plain file name.
+fn resolve(root: &Path, relative: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for segment in relative.split('/').filter(|segment| !segment.is_empty()) {
+        let segment = percent_decode(segment)?;
+        if segment.starts_with('.') || segment.contains(['/', '\\', '\0']) {
+            return None;
+        }
+        path.push(segment);
+    }
+
+    return Some(path);
+}
+
+pub fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    return String::from_utf8(decoded).ok();
+}
+
+/// Whether the opened `file` lies within `root` once symbolic links are resolved.
+async fn is_within_root(root: &Path, file: &tokio::fs::File) -> bool {
+    let (Ok(root), Ok(path)) = (tokio::fs::canonicalize(root).await, opened_path(file).await)
+    else {
+        return false;
+    };
+
+    return path.starts_with(root);
+}
+
+/// The path of the file behind the descriptor of `file`, as resolved by the kernel when it was opened.
+#[cfg(target_os = "linux")]
+async fn opened_path(file: &tokio::fs::File) -> io::Result<PathBuf> {
+    use std::os::fd::AsRawFd;
+
+    return tokio::fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())).await;
+}
+
+#[cfg(target_vendor = "apple")]
+async fn opened_path(file: &tokio::fs::File) -> io::Result<PathBuf> {
+    use std::{ffi::CStr, os::fd::AsRawFd};
+
+    let mut buffer = [0 as libc::c_char; libc::MAXPATHLEN as usize];
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETPATH, buffer.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let path = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+    return Ok(PathBuf::from(path.to_string_lossy().into_owned()));
+}
+
+#[cfg(not(any(target_os = "linux", target_vendor = "apple")))]
+async fn opened_path(_file: &tokio::fs::File) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the path of an open file is only known on Linux and Apple platforms",
+    ))
+}
+
+/// A strong entity tag derived from the size and modification time of a file.
+fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_nanos())
+        .unwrap_or(0);
+    return format!("\"{:x}-{:x}\"", len, modified);
+}
+
+/// Evaluates `If-None-Match`, or `If-Modified-Since` when the former is absent.
+fn is_not_modified(request: &Request<Body>, etag: &str, modified: Option<SystemTime>) -> bool {
+    let headers = request.headers();
+
+    if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+        return if_none_match
+            .to_str()
+            .is_ok_and(|if_none_match| etag::none_match(if_none_match, etag));
+    }
+
+    let if_modified_since = headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok());
+    return match (if_modified_since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    };
+}
+
+fn set_validators(response: &mut Response<Body>, etag: &str, modified: Option<SystemTime>) {
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(etag) {
+        headers.insert(ETAG, etag);
+    }
+    if let Some(modified) = modified
+        && let Ok(last_modified) = HeaderValue::from_str(&httpdate::fmt_http_date(modified))
+    {
+        headers.insert(LAST_MODIFIED, last_modified);
+    }
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    return response;
+}
+
+/// The media type of `path`, from its extension.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use hyper::{Body, Request, StatusCode};
+
+    use super::{StaticMount, serve};
+
+    /// A directory holding a mount root with `public.txt`, next to a `secret.txt` outside of it.
+    struct Fixture {
+        directory: PathBuf,
+        mount: StaticMount,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Fixture {
+            let directory =
+                std::env::temp_dir().join(format!("kiri-{}-{}", name, std::process::id()));
+            let root = directory.join("root");
+            std::fs::create_dir_all(root.join("nested")).expect("create directory");
+            std::fs::write(root.join("public.txt"), "public").expect("write file");
+            std::fs::write(directory.join("secret.txt"), "secret").expect("write file");
+
+            let mount = StaticMount::new("/static", root, vec!["index.html".to_string()]);
+            return Fixture { directory, mount };
+        }
+
+        async fn status(&self, path: &str) -> StatusCode {
+            let request = Request::get(path).body(Body::empty()).expect("request");
+            return serve(&self.mount, &request).await.status();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.directory);
+        }
+    }
+
+    #[tokio::test]
+    async fn symbolic_links_are_followed_within_the_root_only() {
+        let fixture = Fixture::new("symlinks");
+        let root = &fixture.mount.root;
+        std::os::unix::fs::symlink(root.join("public.txt"), root.join("inside.txt"))
+            .expect("symlink");
+        std::os::unix::fs::symlink(
+            fixture.directory.join("secret.txt"),
+            root.join("outside.txt"),
+        )
+        .expect("symlink");
+        std::os::unix::fs::symlink(&fixture.directory, root.join("nested/up")).expect("symlink");
+
+        assert_eq!(fixture.status("/static/public.txt").await, StatusCode::OK);
+        assert_eq!(fixture.status("/static/inside.txt").await, StatusCode::OK);
+        assert_eq!(
+            fixture.status("/static/outside.txt").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            fixture.status("/static/nested/up/secret.txt").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn traversal_and_hidden_files_are_not_served() {
+        let fixture = Fixture::new("traversal");
+        std::fs::write(fixture.mount.root.join(".env"), "hidden").expect("write file");
+
+        assert_eq!(
+            fixture.status("/static/../secret.txt").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            fixture.status("/static/%2e%2e/secret.txt").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            fixture.status("/static/..%2fsecret.txt").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(fixture.status("/static/.env").await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            fixture.status("/static/missing.txt").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of a running server, shared by all its connections.
+#[derive(Default)]
+pub struct ServerStats {
+    pub rejected_uri_too_long: AtomicU64,
+    pub rejected_headers_too_large: AtomicU64,
+    pub rejected_body_too_large: AtomicU64,
+    pub rejected_overloaded: AtomicU64,
+    pub rejected_shed: AtomicU64,
+    pub rejected_rate_limited: AtomicU64,
+    pub average_dispatch_latency_us: AtomicU64,
+    pub open_connections: AtomicU64,
+    pub in_flight_dispatches: AtomicU64,
+    pub queued_dispatches: AtomicU64,
+    pub cache_hits: AtomicU64,
+    pub cache_misses: AtomicU64,
+}
+
+/// A copy of the counters of a server at a point in time.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerStatsSnapshot {
+    /// Requests answered with 414 URI Too Long.
+    pub rejected_uri_too_long: u64,
+    /// Requests answered with 431 Request Header Fields Too Large, for their header bytes or count.
+    pub rejected_headers_too_large: u64,
+    /// Requests answered with 413 Payload Too Large.
+    pub rejected_body_too_large: u64,
+    /// Requests answered with 503 Service Unavailable because the dispatch queue was full.
+    pub rejected_overloaded: u64,
+    /// Requests answered with 503 Service Unavailable by the load shedder.
+    pub rejected_shed: u64,
+    /// Requests answered with 429 Too Many Requests.
+    pub rejected_rate_limited: u64,
+    /// The moving average of the time Swift takes to handle a request, in microseconds.
+    pub average_dispatch_latency_us: u64,
+    /// The connections currently open.
+    pub open_connections: u64,
+    /// The requests currently dispatched to Swift.
+    pub in_flight_dispatches: u64,
+    /// The requests currently waiting for a dispatch slot.
+    pub queued_dispatches: u64,
+    /// Requests of cached routes answered from the response cache, without dispatching to Swift.
+    pub cache_hits: u64,
+    /// Requests of cached routes that found no fresh response in the cache.
+    pub cache_misses: u64,
+}
+
+impl ServerStats {
+    pub fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ServerStatsSnapshot {
+        ServerStatsSnapshot {
+            rejected_uri_too_long: self.rejected_uri_too_long.load(Ordering::Relaxed),
+            rejected_headers_too_large: self.rejected_headers_too_large.load(Ordering::Relaxed),
+            rejected_body_too_large: self.rejected_body_too_large.load(Ordering::Relaxed),
+            rejected_overloaded: self.rejected_overloaded.load(Ordering::Relaxed),
+            rejected_shed: self.rejected_shed.load(Ordering::Relaxed),
+            rejected_rate_limited: self.rejected_rate_limited.load(Ordering::Relaxed),
+            average_dispatch_latency_us: self.average_dispatch_latency_us.load(Ordering::Relaxed),
+            open_connections: self.open_connections.load(Ordering::Relaxed),
+            in_flight_dispatches: self.in_flight_dispatches.load(Ordering::Relaxed),
+            queued_dispatches: self.queued_dispatches.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    sync::{Arc, RwLock},
+};
+
+use rustls::{
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use tokio_rustls::TlsAcceptor;
+
+use crate::{
+    core::config::{CertificateConfig, PemSource},
+    error::KiriError,
+    status::KiriStatus,
+};
+
+/// The TLS state of a listener: the acceptor used for handshakes,
+/// and the certificates it serves, which can be reloaded while the server runs.
+pub struct TlsState {
+    certificates: Vec<CertificateConfig>,
+    provider: Arc<CryptoProvider>,
+    resolver: Arc<SniResolver>,
+    pub acceptor: TlsAcceptor,
+}
+
+impl TlsState {
+    /// Loads `certificates`, advertising HTTP/2 through ALPN when `http2` is enabled.
+    pub fn load(certificates: &[CertificateConfig], http2: bool) -> Result<TlsState, KiriError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let keys = load_keys(certificates, &provider)?;
+        let resolver = Arc::new(SniResolver {
+            keys: RwLock::new(Arc::new(keys)),
+        });
+
+        let mut config = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| KiriError::new(KiriStatus::TlsFailed, e.to_string()))?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone());
+        config.alpn_protocols = match http2 {
+            true => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            false => vec![b"http/1.1".to_vec()],
+        };
+
+        Ok(TlsState {
+            certificates: certificates.to_vec(),
+            provider,
+            resolver,
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        })
+    }
+
+    /// Whether the client of `stream` negotiated HTTP/2 during the handshake.
+    pub fn negotiated_http2<IO>(stream: &tokio_rustls::server::TlsStream<IO>) -> bool {
+        stream.get_ref().1.alpn_protocol() == Some(b"h2")
+    }
+
+    /// Reads the certificates again, so that renewed files are served to new connections.
+    /// On failure the certificates currently served are kept.
+    pub fn reload(&self) -> Result<(), KiriError> {
+        let keys = load_keys(&self.certificates, &self.provider)?;
+        *self
+            .resolver
+            .keys
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Arc::new(keys);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct CertifiedKeys {
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    default: Option<Arc<CertifiedKey>>,
+}
+
+impl CertifiedKeys {
+    /// Selects the certificate for `server_name`: an exact match first, then a wildcard for its parent domain,
+    /// then the default certificate.
+    fn select(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        if let Some(name) = server_name.map(|n| n.to_ascii_lowercase()) {
+            if let Some(key) = self.by_name.get(&name) {
+                return Some(key.clone());
+            }
+
+            if let Some((_, parent)) = name.split_once('.')
+                && let Some(key) = self.by_name.get(&format!("*.{}", parent))
+            {
+                return Some(key.clone());
+            }
+        }
+
+        return self.default.clone();
+    }
+}
+
+/// Selects the certificate of a connection from the server name (SNI) sent by the client.
+#[derive(Debug)]
+struct SniResolver {
+    keys: RwLock<Arc<CertifiedKeys>>,
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner()).clone();
+        keys.select(client_hello.server_name())
+    }
+}
+
+fn load_keys(
+    certificates: &[CertificateConfig],
+    provider: &CryptoProvider,
+) -> Result<CertifiedKeys, KiriError> {
+    let mut keys = CertifiedKeys {
+        by_name: HashMap::new(),
+        default: None,
+    };
+
+    for certificate in certificates {
+        let key = Arc::new(load_key(certificate, provider)?);
+
+        if certificate.server_names.is_empty() {
+            keys.default = Some(key.clone());
+        }
+
+        for name in &certificate.server_names {
+            keys.by_name.insert(name.to_ascii_lowercase(), key.clone());
+        }
+
+        // Without an explicit default, clients that send no matching name get the first certificate.
+        if keys.default.is_none() {
+            keys.default = Some(key);
+        }
+    }
+
+    Ok(keys)
+}
+
+fn load_key(
+    certificate: &CertificateConfig,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, KiriError> {
+    let chain_pem = read_pem(&certificate.chain)?;
+    let chain = CertificateDer::pem_slice_iter(&chain_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tls_error(&certificate.chain, e))?;
+
+    if chain.is_empty() {
+        return Err(tls_error(&certificate.chain, "no certificate found"));
+    }
+
+    let key_pem = read_pem(&certificate.key)?;
+    let key =
+        PrivateKeyDer::from_pem_slice(&key_pem).map_err(|e| tls_error(&certificate.key, e))?;
+
+    CertifiedKey::from_der(chain, key, provider).map_err(|e| tls_error(&certificate.key, e))
+}
+
+fn read_pem(source: &PemSource) -> Result<Cow<'_, [u8]>, KiriError> {
+    match source {
+        PemSource::File(path) => fs::read(path)
+            .map(Cow::Owned)
+            .map_err(|e| tls_error(source, e)),
+        PemSource::Memory(bytes) => Ok(Cow::Borrowed(bytes)),
+    }
+}
+
+fn tls_error(source: &PemSource, error: impl std::fmt::Display) -> KiriError {
+    KiriError::new(KiriStatus::TlsFailed, format!("{}: {}", source, error))
+}
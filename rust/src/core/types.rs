@@ -1,15 +1,54 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::core::{cors::Cors, layers::Layer, rate_limit::RateLimit, static_files::StaticMount};
+
 pub type Port = u16;
 pub type StatusCode = u16;
 pub type HandlerId = u64;
 
+/// Settings of a single route, overriding the server-wide ones.
+#[derive(Clone, Debug, Default)]
+pub struct RouteOptions {
+    /// The maximum size in bytes of a request body, replacing `RequestLimits::max_body_size`.
+    pub max_body_size: Option<u64>,
+    /// The rate limit of the route, replacing `ServerConfig::rate_limit`.
+    pub rate_limit: Option<RateLimit>,
+    /// Whether the responses of the route are sent uncompressed, even when `ServerConfig::compression` is set.
+    pub skip_compression: bool,
+    /// Whether 200 responses without an `ETag` are tagged with the hash of their body,
+    /// and answered with 304 Not Modified when `If-None-Match` matches.
+    pub etag: bool,
+    /// Whether the GET responses of the route are cached when `ServerConfig::cache` is set.
+    pub cache: bool,
+    /// The CORS policy of the route, replacing `ServerConfig::cors`.
+    pub cors: Option<Arc<Cors>>,
+    /// The layers run around the handler of the route, outermost first.
+    pub layers: Vec<Arc<dyn Layer>>,
+}
+
 #[derive(Clone)]
 pub struct Route {
     pub method: u8,
     pub pattern: String,
     pub handler_id: HandlerId,
+    pub options: RouteOptions,
+    /// The directory served by Rust for the route, instead of dispatching to `handler_id`.
+    pub mount: Option<Arc<StaticMount>>,
+}
+
+/// A layer attached to a path prefix, run around every request whose path lies under it.
+#[derive(Clone, Debug)]
+pub struct PrefixLayer {
+    pub prefix: String,
+    pub layer: Arc<dyn Layer>,
+}
+
+/// The routes of a server, and the layers attached to path prefixes, in the order they were attached.
+#[derive(Clone, Default)]
+pub struct RouteTable {
+    pub routes: Vec<Route>,
+    pub prefix_layers: Vec<PrefixLayer>,
 }
 
-pub type SharedRoutes = Arc<RwLock<Vec<Route>>>;
+pub type SharedRoutes = Arc<RwLock<RouteTable>>;
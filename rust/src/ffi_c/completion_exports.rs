@@ -3,7 +3,7 @@ use std::{
     sync::{Arc, atomic::Ordering},
 };
 
-use crate::runtime::completion::*;
+use crate::{error::KiriError, runtime::completion::*, status::KiriStatus};
 
 /// Swift calls this to check if a request has been cancelled.
 #[unsafe(no_mangle)]
@@ -52,9 +52,13 @@ pub extern "C" fn kiri_request_complete(
     completion_ctx: *mut std::ffi::c_void,
     resp_ptr: *const u8,
     resp_len: usize,
-) {
+) -> KiriStatus {
     if completion_ctx.is_null() || resp_ptr.is_null() {
-        return;
+        return KiriError::new(
+            KiriStatus::NullPointer,
+            "completion context or response is null",
+        )
+        .report();
     }
 
     let context = unsafe { Arc::from_raw(completion_ctx as *const CompletionContext) };
@@ -69,7 +73,11 @@ pub extern "C" fn kiri_request_complete(
 
     // If request was already completed or cancelled, we drop the reference.
     if previous_state.is_err() {
-        return;
+        return KiriError::new(
+            KiriStatus::RequestNotPending,
+            "request was already completed or cancelled",
+        )
+        .report();
     }
 
     let bytes = unsafe { slice::from_raw_parts(resp_ptr, resp_len) }.to_vec();
@@ -78,4 +86,6 @@ pub extern "C" fn kiri_request_complete(
     if let Some(transmitter) = guard.take() {
         let _ = transmitter.send(bytes);
     }
+
+    return KiriStatus::Ok;
 }
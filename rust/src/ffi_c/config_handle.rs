@@ -0,0 +1,892 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    os::raw::{c_char, c_void},
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use hyper::header::HeaderName;
+use tokio::sync::Semaphore;
+
+use crate::{
+    core::{
+        arc::arc_from_borrowed_ptr,
+        compression::Encoding,
+        config::{
+            CertificateConfig, Compression, Decompression, ListenerAddress, ListenerConfig,
+            PemSource, RequestIds, ResponseCache, ServerConfig, default_host,
+        },
+        config_handle::ServerConfigHandle,
+        forwarded::Cidr,
+        types::Port,
+    },
+    cors::cors_from_raw_parts,
+    error::KiriError,
+    rate_limit::rate_limit_from_raw_parts,
+    status::KiriStatus,
+    strings::{list_from_raw_parts, new_c_string, str_from_raw_parts},
+};
+
+/// Borrows the configuration handle owned by the client, and applies `update` to its configuration.
+fn update_config(
+    config: *const c_void,
+    update: impl FnOnce(&mut ServerConfig) -> Result<(), KiriError>,
+) -> KiriStatus {
+    if config.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "config is null").report();
+    }
+
+    // Swift owns the configuration, so we borrow the pointer and avoid dropping it.
+    let handle = unsafe { arc_from_borrowed_ptr(config as *const ServerConfigHandle) };
+    let mut config = handle.config.lock().unwrap_or_else(|e| e.into_inner());
+
+    match update(&mut config) {
+        Ok(()) => KiriStatus::Ok,
+        Err(error) => error.report(),
+    }
+}
+
+/// Applies `update` to the listener at `index`.
+fn update_listener(
+    config: *const c_void,
+    index: usize,
+    update: impl FnOnce(&mut ListenerConfig) -> Result<(), KiriError>,
+) -> KiriStatus {
+    update_config(config, |config| match config.listeners.get_mut(index) {
+        Some(listener) => update(listener),
+        None => Err(KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("no listener at index {}", index),
+        )),
+    })
+}
+
+/// Fails when `value` is more permits than a Tokio semaphore holds, which would panic once the server starts.
+fn check_max_permits(value: usize, argument: &str) -> Result<(), KiriError> {
+    if value > Semaphore::MAX_PERMITS {
+        return Err(KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("{} must be at most {}", argument, Semaphore::MAX_PERMITS),
+        ));
+    }
+
+    return Ok(());
+}
+
+/// Maps a timeout in milliseconds to a duration, where `0` disables the timeout.
+fn duration_from_millis(millis: u64) -> Option<Duration> {
+    return (millis > 0).then(|| Duration::from_millis(millis));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_create() -> *mut c_void {
+    let config = Arc::new(ServerConfigHandle::new());
+    Arc::into_raw(config) as *mut c_void
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_free(config: *const c_void) {
+    if config.is_null() {
+        return;
+    }
+
+    unsafe {
+        drop(Arc::from_raw(config as *const ServerConfigHandle));
+    }
+}
+
+/// Adds a TCP listener on `host` (an IPv4 or IPv6 address, without brackets) and `port`,
+/// and writes its index into `out_index`, if not null.
+/// A null `host` binds `KIRI_BIND_HOST` if set, or the IPv4 loopback otherwise.
+/// Port 0 binds an ephemeral port, readable with `kiri_server_bound_port` once started.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_add_tcp_listener(
+    config: *const c_void,
+    host_ptr: *const u8,
+    host_len: usize,
+    port: Port,
+    out_index: *mut usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        let host = if host_ptr.is_null() {
+            default_host()
+        } else {
+            let host = str_from_raw_parts(host_ptr, host_len, "host")?;
+            host.parse::<IpAddr>().map_err(|e| {
+                KiriError::new(
+                    KiriStatus::InvalidArgument,
+                    format!("invalid host {:?}: {}", host, e),
+                )
+            })?
+        };
+
+        config
+            .listeners
+            .push(ListenerConfig::tcp(SocketAddr::new(host, port)));
+
+        if !out_index.is_null() {
+            unsafe {
+                *out_index = config.listeners.len() - 1;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Adds a Unix domain socket listener on the UTF-8 `path`, and writes its index into `out_index`, if not null.
+/// A path starting with `@` binds the rest of the name in the Linux abstract namespace instead of the filesystem.
+/// The filesystem socket is removed when the server stops.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_add_unix_listener(
+    config: *const c_void,
+    path_ptr: *const u8,
+    path_len: usize,
+    out_index: *mut usize,
+) -> KiriStatus {
+    if path_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "path is null").report();
+    }
+
+    update_config(config, |config| {
+        let path = str_from_raw_parts(path_ptr, path_len, "path")?;
+        let address = match path.strip_prefix('@') {
+            Some(name) => ListenerAddress::UnixAbstract(name.as_bytes().to_vec()),
+            None if path.is_empty() => {
+                return Err(KiriError::new(
+                    KiriStatus::InvalidArgument,
+                    "Unix socket path is empty",
+                ));
+            }
+            None => ListenerAddress::Unix(PathBuf::from(path)),
+        };
+
+        config.listeners.push(ListenerConfig::new(address));
+
+        if !out_index.is_null() {
+            unsafe {
+                *out_index = config.listeners.len() - 1;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Sets the permission bits (e.g. `0660`) applied to the filesystem Unix socket of the listener at `index` once bound.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_set_unix_mode(
+    config: *const c_void,
+    index: usize,
+    mode: u32,
+) -> KiriStatus {
+    update_listener(config, index, |listener| {
+        if !matches!(listener.address, ListenerAddress::Unix(_)) {
+            return Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                format!("listener {} is not a filesystem Unix listener", index),
+            ));
+        }
+
+        if mode > 0o7777 {
+            return Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                format!("invalid permission mode {:o}", mode),
+            ));
+        }
+
+        listener.unix_mode = Some(mode);
+        Ok(())
+    })
+}
+
+/// Sets whether a stale filesystem Unix socket at the path of the listener at `index` is removed before binding.
+/// Enabled by default. A socket that still accepts connections is never removed.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_set_remove_stale_socket(
+    config: *const c_void,
+    index: usize,
+    remove_stale_socket: bool,
+) -> KiriStatus {
+    update_listener(config, index, |listener| {
+        listener.remove_stale_socket = remove_stale_socket;
+        Ok(())
+    })
+}
+
+/// Parses a comma-separated list of server names, where a null pointer means no names.
+fn server_names_from_raw_parts(
+    names_ptr: *const u8,
+    names_len: usize,
+) -> Result<Vec<String>, KiriError> {
+    if names_ptr.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let names = str_from_raw_parts(names_ptr, names_len, "server_names")?;
+    Ok(names
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect())
+}
+
+/// Adds a certificate to the listener at `index`, enabling TLS on it.
+/// Returns `Unsupported` unless the library is built with the `tls` feature.
+fn add_certificate(
+    config: *const c_void,
+    index: usize,
+    certificate: Result<CertificateConfig, KiriError>,
+) -> KiriStatus {
+    if !cfg!(feature = "tls") {
+        return KiriError::new(
+            KiriStatus::Unsupported,
+            "TLS requires building kiri_ffi with the `tls` feature",
+        )
+        .report();
+    }
+
+    update_listener(config, index, |listener| {
+        listener.certificates.push(certificate?);
+        Ok(())
+    })
+}
+
+/// Adds a certificate to the listener at `index`, read from the PEM files at the UTF-8 paths
+/// `chain_path` (the certificate chain, leaf first) and `key_path` (the private key).
+/// The files are read when the server starts, and again by `kiri_server_reload_certificates`.
+/// `server_names` is a comma-separated list of names (e.g. `example.com,*.example.com`) the certificate
+/// is selected for through SNI; a null `server_names` makes it the default certificate.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_add_certificate_files(
+    config: *const c_void,
+    index: usize,
+    chain_path_ptr: *const u8,
+    chain_path_len: usize,
+    key_path_ptr: *const u8,
+    key_path_len: usize,
+    server_names_ptr: *const u8,
+    server_names_len: usize,
+) -> KiriStatus {
+    if chain_path_ptr.is_null() || key_path_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "chain_path or key_path is null").report();
+    }
+
+    let certificate = (|| {
+        let chain_path = str_from_raw_parts(chain_path_ptr, chain_path_len, "chain_path")?;
+        let key_path = str_from_raw_parts(key_path_ptr, key_path_len, "key_path")?;
+        Ok(CertificateConfig {
+            server_names: server_names_from_raw_parts(server_names_ptr, server_names_len)?,
+            chain: PemSource::File(PathBuf::from(chain_path)),
+            key: PemSource::File(PathBuf::from(key_path)),
+        })
+    })();
+
+    add_certificate(config, index, certificate)
+}
+
+/// Adds a certificate to the listener at `index` from in-memory PEM data,
+/// which is copied: `chain` holds the certificate chain (leaf first) and `key` the private key.
+/// `server_names` behaves as in `kiri_server_config_listener_add_certificate_files`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_add_certificate_pem(
+    config: *const c_void,
+    index: usize,
+    chain_ptr: *const u8,
+    chain_len: usize,
+    key_ptr: *const u8,
+    key_len: usize,
+    server_names_ptr: *const u8,
+    server_names_len: usize,
+) -> KiriStatus {
+    if chain_ptr.is_null() || key_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "chain or key is null").report();
+    }
+
+    let certificate =
+        server_names_from_raw_parts(server_names_ptr, server_names_len).map(|server_names| {
+            CertificateConfig {
+                server_names,
+                chain: PemSource::Memory(
+                    unsafe { std::slice::from_raw_parts(chain_ptr, chain_len) }.to_vec(),
+                ),
+                key: PemSource::Memory(
+                    unsafe { std::slice::from_raw_parts(key_ptr, key_len) }.to_vec(),
+                ),
+            }
+        });
+
+    add_certificate(config, index, certificate)
+}
+
+/// Sets whether the IPv6 listener at `index` only accepts IPv6 connections.
+/// Listeners are dual-stack by default, so that `::` also accepts IPv4 connections.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_set_ipv6_only(
+    config: *const c_void,
+    index: usize,
+    ipv6_only: bool,
+) -> KiriStatus {
+    update_listener(config, index, |listener| {
+        listener.ipv6_only = ipv6_only;
+        Ok(())
+    })
+}
+
+/// Sets whether connections to the listener at `index` start with a PROXY protocol v1 or v2 header,
+/// whose source address is then reported as the client address. Connections without one are dropped.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_set_proxy_protocol(
+    config: *const c_void,
+    index: usize,
+    proxy_protocol: bool,
+) -> KiriStatus {
+    update_listener(config, index, |listener| {
+        listener.proxy_protocol = proxy_protocol;
+        Ok(())
+    })
+}
+
+/// Returns the status of the listener at `index` from the last start attempt with this configuration,
+/// writing its error message into `out_message` (or null if it bound successfully).
+/// The returned string must be released with `kiri_string_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_listener_error(
+    config: *const c_void,
+    index: usize,
+    out_message: *mut *mut c_char,
+) -> KiriStatus {
+    if config.is_null() || out_message.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "config or out_message is null").report();
+    }
+
+    let handle = unsafe { arc_from_borrowed_ptr(config as *const ServerConfigHandle) };
+    let listener_errors = handle
+        .listener_errors
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    match listener_errors.iter().find(|e| e.index == index) {
+        Some(listener_error) => {
+            unsafe {
+                *out_message = new_c_string(listener_error.error.message.clone());
+            }
+            listener_error.error.status
+        }
+        None => {
+            unsafe {
+                *out_message = std::ptr::null_mut();
+            }
+            KiriStatus::Ok
+        }
+    }
+}
+
+/// Sets whether the server speaks HTTP/2: through ALPN on TLS listeners,
+/// and with prior knowledge (h2c) on cleartext listeners. Enabled by default.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_http2(config: *const c_void, enabled: bool) -> KiriStatus {
+    update_config(config, |config| {
+        config.http2 = enabled;
+        Ok(())
+    })
+}
+
+/// Sets whether the server runs every task on its own thread instead of a pool of worker threads.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_current_thread(
+    config: *const c_void,
+    current_thread: bool,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.runtime.current_thread = current_thread;
+        Ok(())
+    })
+}
+
+/// Sets the number of worker threads of the multi-thread runtime. Must be greater than 0.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_worker_threads(
+    config: *const c_void,
+    worker_threads: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        if worker_threads == 0 {
+            return Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                "worker_threads must be greater than 0",
+            ));
+        }
+
+        config.runtime.worker_threads = Some(worker_threads);
+        Ok(())
+    })
+}
+
+/// Sets the maximum number of threads spawned for blocking work. Must be greater than 0.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_blocking_threads(
+    config: *const c_void,
+    max_blocking_threads: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        if max_blocking_threads == 0 {
+            return Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                "max_blocking_threads must be greater than 0",
+            ));
+        }
+
+        config.runtime.max_blocking_threads = Some(max_blocking_threads);
+        Ok(())
+    })
+}
+
+/// Sets the UTF-8 prefix of the names of the server threads, e.g. `api` names them `api-server`, `api-0`, `api-1`…
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_thread_name_prefix(
+    config: *const c_void,
+    prefix_ptr: *const u8,
+    prefix_len: usize,
+) -> KiriStatus {
+    if prefix_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "prefix is null").report();
+    }
+
+    update_config(config, |config| {
+        let prefix = str_from_raw_parts(prefix_ptr, prefix_len, "prefix")?;
+        if prefix.contains('\0') {
+            return Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                "prefix must not contain NUL characters",
+            ));
+        }
+
+        config.runtime.thread_name_prefix = Some(prefix.to_string());
+        Ok(())
+    })
+}
+
+/// Sets the maximum size in bytes of a request body, 2 MiB by default.
+/// Larger bodies are answered with 413 Payload Too Large without being dispatched.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_body_size(
+    config: *const c_void,
+    max_body_size: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.limits.max_body_size = max_body_size;
+        Ok(())
+    })
+}
+
+/// Sets the maximum length in bytes of the request target (path and query), 8 KiB by default.
+/// Longer targets are answered with 414 URI Too Long.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_uri_length(
+    config: *const c_void,
+    max_uri_length: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.limits.max_uri_length = max_uri_length;
+        Ok(())
+    })
+}
+
+/// Sets the maximum size in bytes of the header names and values of a request, 32 KiB by default.
+/// Larger headers are answered with 431 Request Header Fields Too Large.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_header_bytes(
+    config: *const c_void,
+    max_header_bytes: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.limits.max_header_bytes = max_header_bytes;
+        Ok(())
+    })
+}
+
+/// Sets the maximum number of header fields of a request, 100 by default.
+/// More fields are answered with 431 Request Header Fields Too Large.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_header_count(
+    config: *const c_void,
+    max_header_count: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.limits.max_header_count = max_header_count;
+        Ok(())
+    })
+}
+
+/// Sets how long a client has to send the whole body of a request, 60 seconds by default.
+/// Slower bodies are answered with 408 Request Timeout. `0` disables the timeout.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_body_read_timeout(
+    config: *const c_void,
+    timeout_ms: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.limits.body_read_timeout = duration_from_millis(timeout_ms);
+        Ok(())
+    })
+}
+
+/// Sets how long an HTTP/1 client has to send the head of a request once it started it,
+/// 30 seconds by default. The connection is closed beyond it. `0` disables the timeout.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_header_read_timeout(
+    config: *const c_void,
+    timeout_ms: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.connections.header_read_timeout = duration_from_millis(timeout_ms);
+        Ok(())
+    })
+}
+
+/// Sets how long a connection stays open without any request in flight, 60 seconds by default.
+/// `0` disables the timeout.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_idle_timeout(
+    config: *const c_void,
+    timeout_ms: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.connections.idle_timeout = duration_from_millis(timeout_ms);
+        Ok(())
+    })
+}
+
+/// Sets the number of requests served on a connection before it is gracefully closed.
+/// `0`, the default, serves any number of requests.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_requests_per_connection(
+    config: *const c_void,
+    max_requests: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.connections.max_requests = (max_requests > 0).then_some(max_requests);
+        Ok(())
+    })
+}
+
+/// Sets the number of connections open at once across all listeners. Beyond it, the server stops
+/// accepting until a connection closes. `0`, the default, accepts any number of connections.
+/// Values above `usize::MAX >> 3` are rejected with `KIRI_STATUS_INVALID_ARGUMENT`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_connections(
+    config: *const c_void,
+    max_connections: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        check_max_permits(max_connections, "max_connections")?;
+        config.connections.max_connections = (max_connections > 0).then_some(max_connections);
+        Ok(())
+    })
+}
+
+/// Sets the number of requests dispatched to Swift at once. `0`, the default, dispatches any number.
+/// Values above `usize::MAX >> 3` are rejected with `KIRI_STATUS_INVALID_ARGUMENT`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_concurrent_dispatches(
+    config: *const c_void,
+    max_concurrent: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        check_max_permits(max_concurrent, "max_concurrent")?;
+        config.dispatch.max_concurrent = (max_concurrent > 0).then_some(max_concurrent);
+        Ok(())
+    })
+}
+
+/// Sets the number of requests waiting for a dispatch slot, 1024 by default.
+/// Beyond it, requests are answered with 503 Service Unavailable. `0` rejects any request without a free slot.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_max_queued_dispatches(
+    config: *const c_void,
+    max_queued: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.dispatch.max_queued = max_queued;
+        Ok(())
+    })
+}
+
+/// Enables adaptive load shedding: requests expected to spend longer than `target_latency_ms` in Swift,
+/// including the time waiting for a dispatch slot, are answered with 503 Service Unavailable.
+/// `0`, the default, disables load shedding.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_shedding_target_latency(
+    config: *const c_void,
+    target_latency_ms: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.load_shedding.target_latency = duration_from_millis(target_latency_ms);
+        Ok(())
+    })
+}
+
+/// Sets the delay sent in the `Retry-After` header of 503 responses, in seconds. 1 second by default.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_retry_after(
+    config: *const c_void,
+    retry_after_secs: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.load_shedding.retry_after = Duration::from_secs(retry_after_secs);
+        Ok(())
+    })
+}
+
+/// Rate limits every route without its own rate limit with a token bucket of `capacity` tokens,
+/// refilled at `refill_per_second`. Requests finding their bucket empty are answered with
+/// 429 Too Many Requests without being dispatched. `key` is a `kiri_rate_limit_key`,
+/// and `header_ptr` names the header of `KIRI_RATE_LIMIT_KEY_HEADER`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_rate_limit(
+    config: *const c_void,
+    key: u8,
+    header_ptr: *const u8,
+    header_len: usize,
+    capacity: u32,
+    refill_per_second: f64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        let rate_limit =
+            rate_limit_from_raw_parts(key, header_ptr, header_len, capacity, refill_per_second)?;
+        config.rate_limit = Some(rate_limit);
+        Ok(())
+    })
+}
+
+/// Trusts the `Forwarded` and `X-Forwarded-*` headers sent by the proxies in the UTF-8 range `cidr`,
+/// e.g. `10.0.0.0/8` or a single address, to resolve the client address, scheme and host of requests.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_add_trusted_proxy(
+    config: *const c_void,
+    cidr_ptr: *const u8,
+    cidr_len: usize,
+) -> KiriStatus {
+    if cidr_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "cidr is null").report();
+    }
+
+    update_config(config, |config| {
+        let cidr = str_from_raw_parts(cidr_ptr, cidr_len, "cidr")?;
+        let cidr = Cidr::from_str(cidr).map_err(|e| {
+            KiriError::new(
+                KiriStatus::InvalidArgument,
+                format!("invalid trusted proxy: {}", e),
+            )
+        })?;
+
+        config.trusted_proxies.push(cidr);
+        Ok(())
+    })
+}
+
+/// Compresses the responses of routes not opting out whose body is at least `min_size` bytes, and whose
+/// `Content-Type` matches one of `content_types`. `encodings` lists the offered encodings among `zstd`,
+/// `br`, `gzip` and `deflate` by order of preference, e.g. `br,gzip`. Both are comma-separated UTF-8 lists,
+/// where a null pointer keeps the defaults: all three encodings, and common text formats.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_compression(
+    config: *const c_void,
+    encodings_ptr: *const u8,
+    encodings_len: usize,
+    content_types_ptr: *const u8,
+    content_types_len: usize,
+    min_size: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        let mut compression = Compression {
+            min_size,
+            ..Compression::default()
+        };
+
+        if let Some(encodings) = list_from_raw_parts(encodings_ptr, encodings_len, "encodings")? {
+            compression.encodings = encodings
+                .iter()
+                .map(|token| {
+                    Encoding::from_token(token).ok_or_else(|| {
+                        KiriError::new(
+                            KiriStatus::InvalidArgument,
+                            format!("unsupported encoding {:?}", token),
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+
+        let content_types =
+            list_from_raw_parts(content_types_ptr, content_types_len, "content_types")?;
+        if let Some(content_types) = content_types {
+            compression.content_types = content_types;
+        }
+
+        config.compression = Some(compression);
+        Ok(())
+    })
+}
+
+/// Decodes request bodies sent with a `gzip`, `deflate`, `br` or `zstd` `Content-Encoding` before
+/// dispatching them, answering other encodings with 415 Unsupported Media Type. Decoded bodies larger than
+/// `max_size` bytes are answered with 413 Payload Too Large, where `0` applies the body size limit of the route.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_request_decompression(
+    config: *const c_void,
+    max_size: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        config.decompression = Some(Decompression {
+            max_size: (max_size > 0).then_some(max_size),
+        });
+        Ok(())
+    })
+}
+
+/// Caches the GET responses of the routes opting in, up to `max_size` bytes, and serves them without dispatching
+/// to Swift while fresh. Responses are fresh for their `s-maxage` or `max-age`, or `default_ttl_ms` without either,
+/// where `0` does not cache them, and for at most `max_ttl_ms`, where `0` keeps the default of one hour.
+/// `vary_headers` is a comma-separated UTF-8 list of the request headers keying the cache besides the method,
+/// path and query, e.g. `accept-language`. Responses whose `Vary` lists other headers are not cached.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_response_cache(
+    config: *const c_void,
+    max_size: usize,
+    default_ttl_ms: u64,
+    max_ttl_ms: u64,
+    vary_headers_ptr: *const u8,
+    vary_headers_len: usize,
+) -> KiriStatus {
+    update_config(config, |config| {
+        if max_size == 0 {
+            return Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                "max_size must be greater than zero",
+            ));
+        }
+
+        let vary_headers = list_from_raw_parts(vary_headers_ptr, vary_headers_len, "vary_headers")?
+            .unwrap_or_default()
+            .iter()
+            .map(|header| {
+                HeaderName::from_bytes(header.as_bytes()).map_err(|e| {
+                    KiriError::new(
+                        KiriStatus::InvalidArgument,
+                        format!("invalid header name {:?}: {}", header, e),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        config.cache = Some(ResponseCache {
+            max_size,
+            default_ttl: duration_from_millis(default_ttl_ms),
+            max_ttl: duration_from_millis(max_ttl_ms).unwrap_or(ResponseCache::DEFAULT_MAX_TTL),
+            vary_headers,
+        });
+        Ok(())
+    })
+}
+
+/// Applies a CORS policy to every route without its own: preflight `OPTIONS` requests are answered by Rust, and
+/// the responses to allowed origins get `Access-Control-Allow-*` headers. `origins` lists the allowed origins,
+/// where `*` allows any and matches any part of a pattern like `https://*.example.com`. `methods` lists the allowed
+/// methods, where a null pointer means `GET,HEAD,POST`, and `headers` the allowed request headers, where `*` allows
+/// any. All three are comma-separated UTF-8 lists. `max_age_secs` is how long browsers may cache a preflight,
+/// where `0` leaves it to the browser.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_cors(
+    config: *const c_void,
+    origins_ptr: *const u8,
+    origins_len: usize,
+    methods_ptr: *const u8,
+    methods_len: usize,
+    headers_ptr: *const u8,
+    headers_len: usize,
+    credentials: bool,
+    max_age_secs: u64,
+) -> KiriStatus {
+    update_config(config, |config| {
+        let cors = cors_from_raw_parts(
+            origins_ptr,
+            origins_len,
+            methods_ptr,
+            methods_len,
+            headers_ptr,
+            headers_len,
+            credentials,
+            max_age_secs,
+        )?;
+        config.cors = Some(cors);
+        Ok(())
+    })
+}
+
+/// Sets how requests are identified. Every request gets an id, included in its request frame and echoed in
+/// the UTF-8 `header` of its response, where a null pointer keeps `x-request-id`. With `trust_incoming`, the id
+/// sent in `header` by the client or a proxy in front of the server is used instead of a generated one, when it
+/// is at most 128 visible ASCII characters.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_config_set_request_id(
+    config: *const c_void,
+    header_ptr: *const u8,
+    header_len: usize,
+    trust_incoming: bool,
+) -> KiriStatus {
+    update_config(config, |config| {
+        let mut request_ids = RequestIds {
+            trust_incoming,
+            ..RequestIds::default()
+        };
+        if !header_ptr.is_null() {
+            let header = str_from_raw_parts(header_ptr, header_len, "header")?;
+            request_ids.header = HeaderName::from_bytes(header.as_bytes()).map_err(|e| {
+                KiriError::new(
+                    KiriStatus::InvalidArgument,
+                    format!("invalid header name {:?}: {}", header, e),
+                )
+            })?;
+        }
+
+        config.request_ids = request_ids;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permit_limits_beyond_a_semaphore_are_rejected() {
+        let config = kiri_server_config_create();
+
+        let too_many = Semaphore::MAX_PERMITS + 1;
+        assert_eq!(
+            kiri_server_config_set_max_connections(config, too_many),
+            KiriStatus::InvalidArgument
+        );
+        assert_eq!(
+            kiri_server_config_set_max_concurrent_dispatches(config, too_many),
+            KiriStatus::InvalidArgument
+        );
+
+        let most = Semaphore::MAX_PERMITS;
+        assert_eq!(
+            kiri_server_config_set_max_connections(config, most),
+            KiriStatus::Ok
+        );
+        assert_eq!(
+            kiri_server_config_set_max_concurrent_dispatches(config, most),
+            KiriStatus::Ok
+        );
+
+        kiri_server_config_free(config);
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use hyper::{Method, header::HeaderName};
+
+use crate::{core::cors::Cors, error::KiriError, status::KiriStatus, strings::list_from_raw_parts};
+
+/// Builds a CORS policy from its FFI arguments, comma-separated UTF-8 lists where a null `methods_ptr`
+/// means `GET, HEAD, POST`, and a null `headers_ptr` allows no header beyond the CORS-safelisted ones.
+#[allow(clippy::too_many_arguments)]
+pub fn cors_from_raw_parts(
+    origins_ptr: *const u8,
+    origins_len: usize,
+    methods_ptr: *const u8,
+    methods_len: usize,
+    headers_ptr: *const u8,
+    headers_len: usize,
+    credentials: bool,
+    max_age_secs: u64,
+) -> Result<Cors, KiriError> {
+    let Some(origins) = list_from_raw_parts(origins_ptr, origins_len, "origins")? else {
+        return Err(KiriError::new(KiriStatus::NullPointer, "origins is null"));
+    };
+    if origins.is_empty() {
+        return Err(KiriError::new(
+            KiriStatus::InvalidArgument,
+            "origins must not be empty",
+        ));
+    }
+
+    let methods = match list_from_raw_parts(methods_ptr, methods_len, "methods")? {
+        Some(methods) => methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes()).map_err(|e| {
+                    KiriError::new(
+                        KiriStatus::InvalidArgument,
+                        format!("invalid method {:?}: {}", method, e),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        None => Cors::DEFAULT_METHODS.to_vec(),
+    };
+
+    let headers = list_from_raw_parts(headers_ptr, headers_len, "headers")?.unwrap_or_default();
+    for header in headers.iter().filter(|header| *header != "*") {
+        HeaderName::from_bytes(header.as_bytes()).map_err(|e| {
+            KiriError::new(
+                KiriStatus::InvalidArgument,
+                format!("invalid header name {:?}: {}", header, e),
+            )
+        })?;
+    }
+
+    return Ok(Cors {
+        origins,
+        methods,
+        headers,
+        credentials,
+        max_age: (max_age_secs > 0).then(|| Duration::from_secs(max_age_secs)),
+    });
+}
@@ -1,22 +1,72 @@
-use std::{cell::RefCell, ffi::CString, os::raw::c_char};
+use std::{cell::RefCell, ffi::CString, fmt, os::raw::c_char};
+
+use crate::status::KiriStatus;
+
+/// An error that can cross the FFI boundary as a status code and a message.
+#[derive(Debug)]
+pub struct KiriError {
+    pub status: KiriStatus,
+    pub message: String,
+}
+
+impl KiriError {
+    pub fn new(status: KiriStatus, message: impl Into<String>) -> KiriError {
+        KiriError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Stores the error as the last error of the current thread, and returns its status.
+    pub fn report(self) -> KiriStatus {
+        set_last_error(self.status, self.message);
+        self.status
+    }
+}
+
+impl fmt::Display for KiriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+struct LastError {
+    status: KiriStatus,
+    message: CString,
+}
 
 thread_local! {
-  static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+  static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
 }
 
-pub fn set_last_error(message: String) {
+pub fn set_last_error(status: KiriStatus, message: String) {
     let c = CString::new(message).unwrap_or_else(|_| CString::new("Unknown error").unwrap());
     LAST_ERROR.with(|slot| {
-        *slot.borrow_mut() = Some(c);
+        *slot.borrow_mut() = Some(LastError { status, message: c });
+    })
+}
+
+/// Returns the status of the most recent failure on the calling thread, or `Ok` if no call failed on it yet.
+/// Successful calls leave it as it is, so it only describes the call that just returned an error status.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_last_error_code() -> KiriStatus {
+    LAST_ERROR.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .map(|e| e.status)
+            .unwrap_or(KiriStatus::Ok)
     })
 }
 
+/// Returns a copy of the message of the most recent failure on the calling thread,
+/// or null if no call failed on it yet. Like `kiri_last_error_code`, it is not cleared by successful calls.
+/// The returned string must be released with `kiri_last_error_message_free`.
 #[unsafe(no_mangle)]
 pub extern "C" fn kiri_last_error_message() -> *mut c_char {
     LAST_ERROR.with(|slot| {
         slot.borrow()
             .as_ref()
-            .map(|c| c.clone().into_raw())
+            .map(|e| e.message.clone().into_raw())
             .unwrap_or(std::ptr::null_mut())
     })
 }
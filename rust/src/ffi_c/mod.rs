@@ -1,4 +1,9 @@
 pub mod completion_exports;
+pub mod config_handle;
+pub mod cors;
 pub mod error;
+pub mod rate_limit;
 pub mod router_handle;
 pub mod server_handle;
+pub mod status;
+pub mod strings;
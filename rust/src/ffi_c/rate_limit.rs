@@ -0,0 +1,77 @@
+use hyper::header::HeaderName;
+
+use crate::{
+    core::rate_limit::{RateLimit, RateLimitKey},
+    error::KiriError,
+    status::KiriStatus,
+    strings::str_from_raw_parts,
+};
+
+/// What requests share a rate limit bucket, passed as a `uint8_t` to the rate limit setters.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KiriRateLimitKey {
+    /// One bucket per client IP address.
+    ClientIp = 0,
+    /// One bucket per value of a header, e.g. an API key, falling back to the client IP without it.
+    Header = 1,
+    /// One bucket per route, shared by all clients.
+    Route = 2,
+}
+
+impl TryFrom<u8> for KiriRateLimitKey {
+    type Error = KiriError;
+
+    fn try_from(key: u8) -> Result<Self, Self::Error> {
+        match key {
+            0 => Ok(KiriRateLimitKey::ClientIp),
+            1 => Ok(KiriRateLimitKey::Header),
+            2 => Ok(KiriRateLimitKey::Route),
+            _ => Err(KiriError::new(
+                KiriStatus::InvalidArgument,
+                format!("unknown rate limit key {}", key),
+            )),
+        }
+    }
+}
+
+/// Builds a rate limit from its FFI arguments, where `header_ptr` names the header of `KiriRateLimitKey::Header`.
+pub fn rate_limit_from_raw_parts(
+    key: u8,
+    header_ptr: *const u8,
+    header_len: usize,
+    capacity: u32,
+    refill_per_second: f64,
+) -> Result<RateLimit, KiriError> {
+    if capacity == 0 || !refill_per_second.is_finite() || refill_per_second <= 0.0 {
+        return Err(KiriError::new(
+            KiriStatus::InvalidArgument,
+            "capacity and refill_per_second must be greater than 0",
+        ));
+    }
+
+    let key = match KiriRateLimitKey::try_from(key)? {
+        KiriRateLimitKey::ClientIp => RateLimitKey::ClientIp,
+        KiriRateLimitKey::Route => RateLimitKey::Route,
+        KiriRateLimitKey::Header => {
+            if header_ptr.is_null() {
+                return Err(KiriError::new(KiriStatus::NullPointer, "header is null"));
+            }
+
+            let header = str_from_raw_parts(header_ptr, header_len, "header")?;
+            let name = HeaderName::from_bytes(header.as_bytes()).map_err(|e| {
+                KiriError::new(
+                    KiriStatus::InvalidArgument,
+                    format!("invalid header name {:?}: {}", header, e),
+                )
+            })?;
+            RateLimitKey::Header(name)
+        }
+    };
+
+    return Ok(RateLimit {
+        key,
+        capacity,
+        refill_per_second,
+    });
+}
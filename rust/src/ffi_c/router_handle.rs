@@ -1,9 +1,18 @@
-use std::{os::raw::c_void, sync::Arc};
+use std::{os::raw::c_void, path::PathBuf, sync::Arc};
 
-use crate::core::{
-    arc::arc_from_borrowed_ptr,
-    router_handle::RouterHandle,
-    types::{HandlerId, Route},
+use crate::{
+    core::{
+        arc::arc_from_borrowed_ptr,
+        layers::{self, Layer},
+        router_handle::RouterHandle,
+        static_files::StaticMount,
+        types::{HandlerId, PrefixLayer, Route, RouteOptions},
+    },
+    cors::cors_from_raw_parts,
+    error::KiriError,
+    rate_limit::rate_limit_from_raw_parts,
+    status::KiriStatus,
+    strings::str_from_raw_parts,
 };
 
 #[unsafe(no_mangle)]
@@ -23,7 +32,8 @@ pub extern "C" fn kiri_router_free(router: *const c_void) {
     }
 }
 
-/// Returns 0 on success, non-zero on failures.
+/// Registers a route matching `method` and the UTF-8 `pattern`, dispatched to `handler_id`.
+/// Fails with `RouterFrozen` once a server has been started with the router.
 #[unsafe(no_mangle)]
 pub extern "C" fn kiri_router_register_route(
     router: *const c_void,
@@ -31,23 +41,27 @@ pub extern "C" fn kiri_router_register_route(
     pattern_ptr: *const u8,
     pattern_len: usize,
     handler_id: HandlerId,
-) -> i32 {
+) -> KiriStatus {
     if router.is_null() || pattern_ptr.is_null() {
-        return 1;
+        return KiriError::new(KiriStatus::NullPointer, "router or pattern is null").report();
     }
 
     // Swift owns the Router, so we borrow the pointer and avoid dropping it.
     let router = unsafe { arc_from_borrowed_ptr(router as *const RouterHandle) };
 
     if router.is_frozen() {
-        return 2;
+        return KiriError::new(KiriStatus::RouterFrozen, "router is frozen").report();
     }
 
     let pattern_bytes = unsafe { std::slice::from_raw_parts(pattern_ptr, pattern_len) };
     let pattern = match std::str::from_utf8(pattern_bytes) {
         Ok(s) => s.to_string(),
-        Err(_e) => {
-            return 3;
+        Err(e) => {
+            return KiriError::new(
+                KiriStatus::InvalidUtf8,
+                format!("pattern is not valid UTF-8: {}", e),
+            )
+            .report();
         }
     };
 
@@ -56,7 +70,323 @@ pub extern "C" fn kiri_router_register_route(
         method,
         pattern,
         handler_id,
+        options: RouteOptions::default(),
+        mount: None,
     });
 
-    return 0;
+    return KiriStatus::Ok;
+}
+
+fn mount_from_raw_parts(
+    prefix_ptr: *const u8,
+    prefix_len: usize,
+    root_ptr: *const u8,
+    root_len: usize,
+    index_files_ptr: *const u8,
+    index_files_len: usize,
+) -> Result<StaticMount, KiriError> {
+    let prefix = str_from_raw_parts(prefix_ptr, prefix_len, "prefix")?;
+    let root = str_from_raw_parts(root_ptr, root_len, "root")?;
+    let index_files = match index_files_ptr.is_null() {
+        true => StaticMount::DEFAULT_INDEX_FILES
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        false => str_from_raw_parts(index_files_ptr, index_files_len, "index_files")?
+            .split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect(),
+    };
+
+    return Ok(StaticMount::new(prefix, PathBuf::from(root), index_files));
+}
+
+/// Mounts the directory at the UTF-8 path `root` under the UTF-8 path `prefix`, served by Rust without
+/// dispatching to Swift. Requests to a directory are served its first existing file among the comma-separated
+/// `index_files`, where a null pointer means `index.html`. `handler_id` identifies the mount for the
+/// `kiri_router_set_route_*` options, and is never dispatched.
+/// Fails with `RouterFrozen` once a server has been started with the router.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_mount_directory(
+    router: *const c_void,
+    prefix_ptr: *const u8,
+    prefix_len: usize,
+    root_ptr: *const u8,
+    root_len: usize,
+    index_files_ptr: *const u8,
+    index_files_len: usize,
+    handler_id: HandlerId,
+) -> KiriStatus {
+    if router.is_null() || prefix_ptr.is_null() || root_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "router, prefix or root is null").report();
+    }
+
+    let router = unsafe { arc_from_borrowed_ptr(router as *const RouterHandle) };
+
+    if router.is_frozen() {
+        return KiriError::new(KiriStatus::RouterFrozen, "router is frozen").report();
+    }
+
+    let mount = match mount_from_raw_parts(
+        prefix_ptr,
+        prefix_len,
+        root_ptr,
+        root_len,
+        index_files_ptr,
+        index_files_len,
+    ) {
+        Ok(mount) => mount,
+        Err(error) => return error.report(),
+    };
+
+    let mut routes = router.routes.blocking_write();
+    routes.push(Route {
+        method: 0,
+        pattern: mount.prefix.clone(),
+        handler_id,
+        options: RouteOptions::default(),
+        mount: Some(Arc::new(mount)),
+    });
+
+    return KiriStatus::Ok;
+}
+
+/// Applies `update` to the options of the routes dispatched to `handler_id`.
+/// Fails with `RouterFrozen` once a server has been started with the router.
+fn update_route_options(
+    router: *const c_void,
+    handler_id: HandlerId,
+    update: impl Fn(&mut RouteOptions),
+) -> KiriStatus {
+    if router.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "router is null").report();
+    }
+
+    let router = unsafe { arc_from_borrowed_ptr(router as *const RouterHandle) };
+
+    if router.is_frozen() {
+        return KiriError::new(KiriStatus::RouterFrozen, "router is frozen").report();
+    }
+
+    let mut routes = router.routes.blocking_write();
+    let mut found = false;
+    for route in routes.iter_mut().filter(|r| r.handler_id == handler_id) {
+        update(&mut route.options);
+        found = true;
+    }
+
+    if !found {
+        return KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("no route is registered for handler {}", handler_id),
+        )
+        .report();
+    }
+
+    return KiriStatus::Ok;
+}
+
+/// Sets the maximum size in bytes of the request bodies accepted by the routes of `handler_id`,
+/// overriding the server-wide limit.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_set_route_max_body_size(
+    router: *const c_void,
+    handler_id: HandlerId,
+    max_body_size: u64,
+) -> KiriStatus {
+    update_route_options(router, handler_id, |options| {
+        options.max_body_size = Some(max_body_size);
+    })
+}
+
+/// Rate limits the routes of `handler_id` with their own token bucket, replacing the server-wide rate limit.
+/// See `kiri_server_config_set_rate_limit`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_set_route_rate_limit(
+    router: *const c_void,
+    handler_id: HandlerId,
+    key: u8,
+    header_ptr: *const u8,
+    header_len: usize,
+    capacity: u32,
+    refill_per_second: f64,
+) -> KiriStatus {
+    let rate_limit =
+        match rate_limit_from_raw_parts(key, header_ptr, header_len, capacity, refill_per_second) {
+            Ok(rate_limit) => rate_limit,
+            Err(error) => return error.report(),
+        };
+
+    update_route_options(router, handler_id, |options| {
+        options.rate_limit = Some(rate_limit.clone());
+    })
+}
+
+/// Sets whether the responses of the routes of `handler_id` are compressed when the server compresses
+/// responses. Routes are compressed by default.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_set_route_compression(
+    router: *const c_void,
+    handler_id: HandlerId,
+    enabled: bool,
+) -> KiriStatus {
+    update_route_options(router, handler_id, |options| {
+        options.skip_compression = !enabled;
+    })
+}
+
+/// Sets whether the 200 responses of the routes of `handler_id` that have no `ETag` are tagged with
+/// a strong `ETag` hashed from their body, and answered with 304 Not Modified when `If-None-Match` matches.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_set_route_etag(
+    router: *const c_void,
+    handler_id: HandlerId,
+    enabled: bool,
+) -> KiriStatus {
+    update_route_options(router, handler_id, |options| {
+        options.etag = enabled;
+    })
+}
+
+/// Sets whether the GET responses of the routes of `handler_id` are cached, when the server has a response cache.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_set_route_cache(
+    router: *const c_void,
+    handler_id: HandlerId,
+    enabled: bool,
+) -> KiriStatus {
+    update_route_options(router, handler_id, |options| {
+        options.cache = enabled;
+    })
+}
+
+/// Applies a CORS policy to the routes of `handler_id`, replacing the server-wide one.
+/// See `kiri_server_config_set_cors`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_set_route_cors(
+    router: *const c_void,
+    handler_id: HandlerId,
+    origins_ptr: *const u8,
+    origins_len: usize,
+    methods_ptr: *const u8,
+    methods_len: usize,
+    headers_ptr: *const u8,
+    headers_len: usize,
+    credentials: bool,
+    max_age_secs: u64,
+) -> KiriStatus {
+    let cors = match cors_from_raw_parts(
+        origins_ptr,
+        origins_len,
+        methods_ptr,
+        methods_len,
+        headers_ptr,
+        headers_len,
+        credentials,
+        max_age_secs,
+    ) {
+        Ok(cors) => Arc::new(cors),
+        Err(error) => return error.report(),
+    };
+
+    update_route_options(router, handler_id, |options| {
+        options.cors = Some(cors.clone());
+    })
+}
+
+fn layer_from_raw_parts(
+    name_ptr: *const u8,
+    name_len: usize,
+    config_ptr: *const u8,
+    config_len: usize,
+) -> Result<Arc<dyn Layer>, KiriError> {
+    if name_ptr.is_null() {
+        return Err(KiriError::new(KiriStatus::NullPointer, "name is null"));
+    }
+
+    let name = str_from_raw_parts(name_ptr, name_len, "name")?;
+    let config = match config_ptr.is_null() {
+        true => "",
+        false => str_from_raw_parts(config_ptr, config_len, "config")?,
+    };
+
+    return layers::build(name, config).map_err(|message| {
+        KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("invalid layer {}: {}", name, message),
+        )
+    });
+}
+
+/// Attaches the built-in layer `name`, configured by the UTF-8 `config`, to every request whose path lies under
+/// the UTF-8 path `prefix`, whatever route or static mount serves it, including the ones answered with 404 for
+/// lack of a route, where `/` means all requests.
+/// A `:param` segment of the prefix matches any segment, and paths are compared once percent-decoded.
+/// Layers run in the order they are attached, those of prefixes before those of routes, on the request before
+/// it is dispatched, and in reverse order on its response. `config` holds lines of `key=value` whose keys
+/// depend on the layer, where a null pointer means no line:
+///
+/// - `response_headers`: each line is a header set on responses that do not have it.
+/// - `ip_filter`: `allow` and `deny` list client addresses or CIDR ranges, answering others with 403 Forbidden.
+/// - `api_key`: answers requests whose `header` (`x-api-key` by default) is not one of `keys` with 401 Unauthorized.
+/// - `server_timing`: adds the time spent handling the request to a `Server-Timing` header.
+///
+/// Fails with `RouterFrozen` once a server has been started with the router.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_add_layer(
+    router: *const c_void,
+    prefix_ptr: *const u8,
+    prefix_len: usize,
+    name_ptr: *const u8,
+    name_len: usize,
+    config_ptr: *const u8,
+    config_len: usize,
+) -> KiriStatus {
+    if router.is_null() || prefix_ptr.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "router or prefix is null").report();
+    }
+
+    let router = unsafe { arc_from_borrowed_ptr(router as *const RouterHandle) };
+
+    if router.is_frozen() {
+        return KiriError::new(KiriStatus::RouterFrozen, "router is frozen").report();
+    }
+
+    let prefix = match str_from_raw_parts(prefix_ptr, prefix_len, "prefix") {
+        Ok(prefix) => prefix.to_string(),
+        Err(error) => return error.report(),
+    };
+    let layer = match layer_from_raw_parts(name_ptr, name_len, config_ptr, config_len) {
+        Ok(layer) => layer,
+        Err(error) => return error.report(),
+    };
+
+    router
+        .prefix_layers
+        .blocking_write()
+        .push(PrefixLayer { prefix, layer });
+    return KiriStatus::Ok;
+}
+
+/// Attaches the built-in layer `name` to the routes of `handler_id`, inside the layers of their prefixes.
+/// See `kiri_router_add_layer`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_router_add_route_layer(
+    router: *const c_void,
+    handler_id: HandlerId,
+    name_ptr: *const u8,
+    name_len: usize,
+    config_ptr: *const u8,
+    config_len: usize,
+) -> KiriStatus {
+    let layer = match layer_from_raw_parts(name_ptr, name_len, config_ptr, config_len) {
+        Ok(layer) => layer,
+        Err(error) => return error.report(),
+    };
+
+    update_route_options(router, handler_id, |options| {
+        options.layers.push(layer.clone());
+    })
 }
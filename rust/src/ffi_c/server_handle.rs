@@ -1,56 +1,135 @@
-use std::{os::raw::c_void, sync::Arc};
+use std::{
+    os::raw::{c_char, c_void},
+    sync::Arc,
+};
 
 use tokio::sync::RwLock;
 
 use crate::{
     core::{
         arc::arc_from_borrowed_ptr,
+        config::{ListenerAddress, ServerConfig},
+        config_handle::ServerConfigHandle,
         router_handle::RouterHandle,
-        server::{ServerHandle, start_server},
-        types::{Port, SharedRoutes},
+        server::{ServerHandle, StartupError, start_server},
+        stats::ServerStatsSnapshot,
+        types::{Port, RouteTable, SharedRoutes},
     },
-    error::set_last_error,
+    error::KiriError,
+    status::KiriStatus,
+    strings::new_c_string,
 };
 
-/// Starts the server with empty routes and returns the server handle.
-/// Available for backwards compatibility.
+/// Writes the started server into `out_handle`, or reports the startup error.
+fn publish(
+    result: Result<ServerHandle, StartupError>,
+    out_handle: *mut *mut ServerHandle,
+) -> KiriStatus {
+    match result {
+        Ok(handle) => {
+            unsafe {
+                *out_handle = Box::into_raw(Box::new(handle));
+            }
+            KiriStatus::Ok
+        }
+        Err(error) => error.error.report(),
+    }
+}
+
+/// Freezes the router and returns a snapshot of its routes and prefix layers for the server to use.
+fn snapshot_routes(router: *const c_void) -> SharedRoutes {
+    let router = unsafe { arc_from_borrowed_ptr(router as *const RouterHandle) };
+
+    // Freeze the router to prevent new routes from being added.
+    router.freeze();
+
+    let snapshot = router.snapshot();
+
+    Arc::new(RwLock::new(snapshot))
+}
+
+/// Starts the server with empty routes and writes the server handle into `out_handle`.
+/// Available for backwards compatibility, with the limits of `kiri_server_start_with_router`.
 #[unsafe(no_mangle)]
-pub extern "C" fn kiri_server_start(port: Port) -> *mut ServerHandle {
-    let routes: SharedRoutes = Arc::new(RwLock::new(Vec::new()));
-    start_server(port, routes)
+pub extern "C" fn kiri_server_start(port: Port, out_handle: *mut *mut ServerHandle) -> KiriStatus {
+    if out_handle.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "out_handle is null").report();
+    }
+
+    let routes: SharedRoutes = Arc::new(RwLock::new(RouteTable::default()));
+    publish(
+        start_server(ServerConfig::unconfigured(port), routes),
+        out_handle,
+    )
 }
 
-/// Starts the server and returns the server handle.
+/// Starts the server and writes the server handle into `out_handle`.
+/// Request bodies are not limited in size, and connections have no header-read, body-read or idle timeout:
+/// use `kiri_server_start_with_config` for the default limits.
 #[unsafe(no_mangle)]
 pub extern "C" fn kiri_server_start_with_router(
     port: Port,
     router: *const c_void,
-) -> *mut ServerHandle {
-    if router.is_null() {
-        set_last_error("router is null".to_string());
-        return std::ptr::null_mut();
+    out_handle: *mut *mut ServerHandle,
+) -> KiriStatus {
+    if router.is_null() || out_handle.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "router or out_handle is null").report();
     }
 
-    let router = unsafe { arc_from_borrowed_ptr(router as *const RouterHandle) };
+    let routes = snapshot_routes(router);
+    publish(
+        start_server(ServerConfig::unconfigured(port), routes),
+        out_handle,
+    )
+}
 
-    // Freeze the router to prevent new routes from being added.
-    router.freeze();
+/// Starts the server with the listeners and options of `config`, and writes the server handle into `out_handle`.
+/// The configuration is copied, so it can be released or reused once this returns.
+/// When listeners fail to bind, each failure is readable with `kiri_server_config_listener_error`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_start_with_config(
+    config: *const c_void,
+    router: *const c_void,
+    out_handle: *mut *mut ServerHandle,
+) -> KiriStatus {
+    if config.is_null() || router.is_null() || out_handle.is_null() {
+        return KiriError::new(
+            KiriStatus::NullPointer,
+            "config, router or out_handle is null",
+        )
+        .report();
+    }
 
-    let snapshot = {
-        let guard = router.routes.blocking_read();
-        guard.clone()
-    };
+    let config = unsafe { arc_from_borrowed_ptr(config as *const ServerConfigHandle) };
+    let snapshot = config
+        .config
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
 
-    let routes: SharedRoutes = Arc::new(RwLock::new(snapshot));
+    let routes = snapshot_routes(router);
+    let result = start_server(snapshot, routes);
 
-    start_server(port, routes)
+    let mut listener_errors = config
+        .listener_errors
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    listener_errors.clear();
+
+    match result {
+        Ok(handle) => publish(Ok(handle), out_handle),
+        Err(mut error) => {
+            listener_errors.append(&mut error.listener_errors);
+            publish(Err(error), out_handle)
+        }
+    }
 }
 
-/// Stops the server managed by the passed handle
+/// Stops the server managed by the passed handle, and releases the handle.
 #[unsafe(no_mangle)]
-pub extern "C" fn kiri_server_stop(handle: *mut ServerHandle) {
+pub extern "C" fn kiri_server_stop(handle: *mut ServerHandle) -> KiriStatus {
     if handle.is_null() {
-        return;
+        return KiriError::new(KiriStatus::NullPointer, "server handle is null").report();
     }
 
     let mut boxed = unsafe { Box::from_raw(handle) };
@@ -62,4 +141,139 @@ pub extern "C" fn kiri_server_stop(handle: *mut ServerHandle) {
     if let Some(join) = boxed.join.take() {
         let _ = join.join();
     }
+
+    return KiriStatus::Ok;
+}
+
+/// Returns the number of addresses the server is listening on.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_bound_address_count(handle: *const ServerHandle) -> usize {
+    if handle.is_null() {
+        return 0;
+    }
+
+    let handle = unsafe { &*handle };
+    handle.bound_addresses.len()
+}
+
+/// Writes the address at `index` the server is listening on into `out_address`,
+/// formatted as `ip:port` for TCP listeners, and `unix:path` or `unix:@name` for Unix listeners.
+/// The returned string must be released with `kiri_string_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_bound_address(
+    handle: *const ServerHandle,
+    index: usize,
+    out_address: *mut *mut c_char,
+) -> KiriStatus {
+    if handle.is_null() || out_address.is_null() {
+        return KiriError::new(
+            KiriStatus::NullPointer,
+            "server handle or out_address is null",
+        )
+        .report();
+    }
+
+    let handle = unsafe { &*handle };
+    let Some(address) = handle.bound_addresses.get(index) else {
+        return KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("no bound address at index {}", index),
+        )
+        .report();
+    };
+
+    unsafe {
+        *out_address = new_c_string(address.to_string());
+    }
+
+    return KiriStatus::Ok;
+}
+
+/// Writes the port of the address at `index` the server is listening on into `out_port`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_bound_port(
+    handle: *const ServerHandle,
+    index: usize,
+    out_port: *mut Port,
+) -> KiriStatus {
+    if handle.is_null() || out_port.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "server handle or out_port is null")
+            .report();
+    }
+
+    let handle = unsafe { &*handle };
+    let Some(address) = handle.bound_addresses.get(index) else {
+        return KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("no bound address at index {}", index),
+        )
+        .report();
+    };
+
+    let ListenerAddress::Tcp(address) = address else {
+        return KiriError::new(
+            KiriStatus::InvalidArgument,
+            format!("listener {} is not a TCP listener", index),
+        )
+        .report();
+    };
+
+    unsafe {
+        *out_port = address.port();
+    }
+
+    return KiriStatus::Ok;
+}
+
+/// Reloads the certificates of every TLS listener of the server from their files,
+/// so that renewed certificates are served to new connections without a restart.
+/// In-memory certificates are kept as they are. If any certificate fails to load,
+/// the listener keeps serving its previous certificates and the error is returned.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_reload_certificates(handle: *const ServerHandle) -> KiriStatus {
+    if handle.is_null() {
+        return KiriError::new(KiriStatus::NullPointer, "server handle is null").report();
+    }
+
+    #[cfg(feature = "tls")]
+    {
+        let handle = unsafe { &*handle };
+        for tls in &handle.tls {
+            if let Err(error) = tls.reload() {
+                return error.report();
+            }
+        }
+
+        return KiriStatus::Ok;
+    }
+
+    #[cfg(not(feature = "tls"))]
+    return KiriError::new(
+        KiriStatus::Unsupported,
+        "TLS requires building kiri_ffi with the `tls` feature",
+    )
+    .report();
+}
+
+/// Writes the current counters of the server into `out_stats`.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_server_get_stats(
+    handle: *const ServerHandle,
+    out_stats: *mut ServerStatsSnapshot,
+) -> KiriStatus {
+    if handle.is_null() || out_stats.is_null() {
+        return KiriError::new(
+            KiriStatus::NullPointer,
+            "server handle or out_stats is null",
+        )
+        .report();
+    }
+
+    let handle = unsafe { &*handle };
+
+    unsafe {
+        *out_stats = handle.stats.snapshot();
+    }
+
+    return KiriStatus::Ok;
 }
@@ -0,0 +1,31 @@
+/// Status code returned by every fallible export of the C API.
+///
+/// `KIRI_STATUS_OK` is always `0`; any other value identifies the failure domain.
+/// The detailed message of the last failure on the calling thread is available
+/// through `kiri_last_error_message`, and its code through `kiri_last_error_code`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KiriStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// The router is frozen because a server was started with it, so it can no longer be mutated.
+    RouterFrozen = 2,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 3,
+    /// An argument had a value outside of its accepted range or format.
+    InvalidArgument = 4,
+    /// The server could not bind one of its listener addresses.
+    BindFailed = 5,
+    /// The request was already completed or cancelled, so the completion was discarded.
+    RequestNotPending = 6,
+    /// An unexpected internal failure, e.g. the server thread exited before reporting its startup.
+    Internal = 7,
+    /// The requested feature was not compiled into this build of the library (e.g. the `tls` cargo feature).
+    Unsupported = 8,
+    /// A TLS certificate chain or private key could not be loaded.
+    TlsFailed = 9,
+    /// The Tokio runtime or the server thread could not be created.
+    RuntimeFailed = 10,
+}
@@ -0,0 +1,59 @@
+use std::{ffi::CString, os::raw::c_char};
+
+use crate::{error::KiriError, status::KiriStatus};
+
+/// Moves `value` into a C string owned by the caller, to be released with `kiri_string_free`.
+pub fn new_c_string(value: String) -> *mut c_char {
+    CString::new(value)
+        .unwrap_or_else(|_| CString::new("").unwrap())
+        .into_raw()
+}
+
+/// Releases a string returned by the C API.
+#[unsafe(no_mangle)]
+pub extern "C" fn kiri_string_free(s: *mut c_char) {
+    if s.is_null() {
+        return;
+    }
+
+    unsafe {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Borrows `len` bytes at `ptr` as a UTF-8 string.
+///
+/// `ptr` must not be null, and must point to at least `len` readable bytes
+/// that outlive the returned string.
+pub fn str_from_raw_parts<'a>(
+    ptr: *const u8,
+    len: usize,
+    argument: &str,
+) -> Result<&'a str, KiriError> {
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+    std::str::from_utf8(bytes).map_err(|e| {
+        KiriError::new(
+            KiriStatus::InvalidUtf8,
+            format!("{} is not valid UTF-8: {}", argument, e),
+        )
+    })
+}
+
+/// Parses a comma-separated UTF-8 list into lowercased items, where a null pointer means `None`.
+pub fn list_from_raw_parts(
+    list_ptr: *const u8,
+    list_len: usize,
+    name: &str,
+) -> Result<Option<Vec<String>>, KiriError> {
+    if list_ptr.is_null() {
+        return Ok(None);
+    }
+
+    let list = str_from_raw_parts(list_ptr, list_len, name)?;
+    Ok(Some(
+        list.split(',')
+            .map(|item| item.trim().to_ascii_lowercase())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    ))
+}
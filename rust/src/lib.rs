@@ -1,5 +1,12 @@
+// Explicit returns are the house style.
+#![allow(clippy::needless_return)]
+
 pub mod core;
+// Exports validate their raw pointers by hand.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub mod ffi_c;
 pub mod runtime;
+#[cfg(test)]
+mod testing;
 
 pub use ffi_c::*;
@@ -35,7 +35,8 @@ pub async fn dispatch_to_swift(
         transmitter: Mutex::new(Some(transmitter)),
     });
 
-    // Hyper drops the request future (handled with dispatch_to_swift) when the client disconnects.
+    // Hyper drops the request future (handled with dispatch_to_swift) when the client disconnects,
+    // and on HTTP/2 when the client resets the stream of the request (RST_STREAM), leaving the connection open.
     // When dispatch_to_swift is dropped, this guard runs.
     let _cancel_on_drop = CancelOnDrop {
         context: context.clone(),
@@ -0,0 +1,223 @@
+//! Support for the tests of the crate: a stand-in for the Swift runtime, and servers started through the C API.
+
+use std::{
+    collections::HashMap,
+    ffi::c_void,
+    io::{Read, Write},
+    net::TcpStream,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    core::{server::ServerHandle, types::HandlerId},
+    ffi_c::{
+        completion_exports::{
+            kiri_cancellation_free, kiri_request_complete, kiri_request_free,
+            kiri_request_is_cancelled,
+        },
+        config_handle::{
+            kiri_server_config_add_tcp_listener, kiri_server_config_create, kiri_server_config_free,
+        },
+        router_handle::{kiri_router_create, kiri_router_free},
+        server_handle::{kiri_server_bound_port, kiri_server_start_with_config, kiri_server_stop},
+    },
+    status::KiriStatus,
+};
+
+/// Handlers whose requests are held pending until a test completes them, instead of being answered at once.
+pub const HELD_HANDLERS: std::ops::RangeFrom<HandlerId> = 1_000..;
+
+/// A request dispatched to a held handler, still owned by the test.
+pub struct Dispatched {
+    pub context: *mut c_void,
+    pub cancellation: *mut c_void,
+}
+
+// The pointers are only handed back to the C API, which is thread safe.
+unsafe impl Send for Dispatched {}
+
+impl Dispatched {
+    pub fn is_cancelled(&self) -> bool {
+        return kiri_request_is_cancelled(self.cancellation);
+    }
+}
+
+impl Drop for Dispatched {
+    fn drop(&mut self) {
+        kiri_request_free(self.context);
+        kiri_cancellation_free(self.cancellation);
+    }
+}
+
+static DISPATCHED: Mutex<Vec<(HandlerId, Dispatched)>> = Mutex::new(Vec::new());
+
+fn dispatched() -> MutexGuard<'static, Vec<(HandlerId, Dispatched)>> {
+    return DISPATCHED.lock().unwrap_or_else(|e| e.into_inner());
+}
+
+/// Answers requests with 200 and the handler id as body, or holds them for handlers of `HELD_HANDLERS`.
+#[unsafe(no_mangle)]
+extern "C" fn swift_dispatch(
+    handler_id: HandlerId,
+    _req_ptr: *const u8,
+    _req_len: usize,
+    completion_ctx: *mut c_void,
+    cancellation_handle: *mut c_void,
+) {
+    if HELD_HANDLERS.contains(&handler_id) {
+        let request = Dispatched {
+            context: completion_ctx,
+            cancellation: cancellation_handle,
+        };
+        dispatched().push((handler_id, request));
+        return;
+    }
+
+    // Swift completes requests from its own threads, never from the one dispatching them.
+    let context = completion_ctx as usize;
+    let cancellation = cancellation_handle as usize;
+    std::thread::spawn(move || {
+        let body = handler_id.to_string();
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&200u16.to_le_bytes());
+        frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        frame.extend_from_slice(body.as_bytes());
+        kiri_request_complete(context as *mut c_void, frame.as_ptr(), frame.len());
+        kiri_cancellation_free(cancellation as *const c_void);
+    });
+}
+
+/// Waits for a request dispatched to the held handler `handler_id`.
+pub fn wait_for_dispatch(handler_id: HandlerId) -> Dispatched {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        {
+            let mut dispatched = dispatched();
+            if let Some(index) = dispatched.iter().position(|(id, _)| *id == handler_id) {
+                return dispatched.remove(index).1;
+            }
+        }
+        assert!(
+            Instant::now() < deadline,
+            "handler {} was not dispatched",
+            handler_id
+        );
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Waits until `condition` holds, failing the test after `within`.
+pub fn wait_until(what: &str, within: Duration, condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + within;
+    while !condition() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting until {}",
+            what
+        );
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// A server listening on an ephemeral port of the IPv4 loopback, stopped when dropped.
+pub struct TestServer {
+    handle: *mut ServerHandle,
+    pub port: u16,
+}
+
+impl TestServer {
+    /// Starts a server with the routes added by `routes` to a router, and the configuration set by `configure`.
+    pub fn start(
+        routes: impl FnOnce(*const c_void),
+        configure: impl FnOnce(*const c_void),
+    ) -> Self {
+        let router = kiri_router_create();
+        routes(router);
+
+        let config = kiri_server_config_create();
+        let mut index = 0;
+        let host = "127.0.0.1";
+        let status =
+            kiri_server_config_add_tcp_listener(config, host.as_ptr(), host.len(), 0, &mut index);
+        assert_eq!(status, KiriStatus::Ok);
+        configure(config);
+
+        let mut handle = std::ptr::null_mut();
+        let status = kiri_server_start_with_config(config, router, &mut handle);
+        kiri_server_config_free(config);
+        kiri_router_free(router);
+        assert_eq!(status, KiriStatus::Ok);
+
+        let mut port = 0;
+        assert_eq!(kiri_server_bound_port(handle, 0, &mut port), KiriStatus::Ok);
+        return TestServer { handle, port };
+    }
+
+    /// Sends an HTTP/1.1 GET request for `path` with `headers`, and reads its response.
+    pub fn get(&self, path: &str, headers: &[(&str, &str)]) -> Response {
+        return self.request("GET", path, headers);
+    }
+
+    pub fn request(&self, method: &str, path: &str, headers: &[(&str, &str)]) -> Response {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).expect("connect");
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n",
+            method, path
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).expect("write");
+
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).expect("read");
+        return Response::parse(&bytes);
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        kiri_server_stop(self.handle);
+    }
+}
+
+/// A response read from a test server.
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    /// The headers, with lowercased names.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn parse(bytes: &[u8]) -> Response {
+        let end = bytes
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .expect("complete response head");
+        let head = String::from_utf8_lossy(&bytes[..end]);
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .and_then(|line| line.split(' ').nth(1))
+            .and_then(|status| status.parse().ok())
+            .expect("status line");
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        return Response {
+            status,
+            headers,
+            body: bytes[end + 4..].to_vec(),
+        };
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        return self.headers.get(name).map(|value| value.as_str());
+    }
+}
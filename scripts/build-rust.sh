@@ -5,6 +5,8 @@ SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 ROOT_DIR="$(cd "${SCRIPT_DIR}/.." && pwd)"
 
 MODE="${1:-release}"
+# Optional comma-separated cargo features, e.g. `tls`.
+FEATURES="${2:-}"
 
 PROFILE="release"
 CARGO_FLAGS=()
@@ -23,11 +25,19 @@ case "$MODE" in
     CARGO_FLAGS=(--release --features bench)
     ;;
   *)
-    echo "Usage: $0 [release|debug|bench]"
+    echo "Usage: $0 [release|debug|bench] [features]"
     exit 1
     ;;
 esac
 
+if [[ -n "$FEATURES" ]]; then
+  if [[ "$MODE" == "bench" ]]; then
+    CARGO_FLAGS=(--release --features "bench,${FEATURES}")
+  else
+    CARGO_FLAGS+=(--features "$FEATURES")
+  fi
+fi
+
 echo "Building libkiri_ffi (${MODE})."
 
 (
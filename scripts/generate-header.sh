@@ -0,0 +1,19 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+ROOT_DIR="$(cd "${SCRIPT_DIR}/.." && pwd)"
+
+HEADER_PATH="${ROOT_DIR}/swift/Sources/KiriFFI/include/kiri_ffi.h"
+
+if ! command -v cbindgen >/dev/null 2>&1; then
+  echo "cbindgen not found. Install it with: cargo install cbindgen"
+  exit 1
+fi
+
+(
+  cd "${ROOT_DIR}/rust"
+  cbindgen --config cbindgen.toml --crate kiri_ffi --output "$HEADER_PATH"
+)
+
+echo "Generated ${HEADER_PATH}."
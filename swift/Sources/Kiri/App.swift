@@ -4,8 +4,28 @@ import Foundation
 final public class App {
   private let server: Server
 
-  public init(port: Port, router: Router) {
-    server = Server(port: port, router: router)
+  public convenience init(port: Port, router: Router) {
+    self.init(configuration: ServerConfiguration(port: port), router: router)
+  }
+
+  public init(configuration: ServerConfiguration, router: Router) {
+    server = Server(configuration: configuration, router: router)
+  }
+
+  /// The addresses the server is listening on, e.g. `127.0.0.1:54321` when started on port `0`.
+  public var boundAddresses: [String] {
+    server.boundAddresses
+  }
+
+  /// The counters of the running server, such as the requests rejected for exceeding the limits.
+  public var stats: ServerStats? {
+    server.stats
+  }
+
+  /// Reloads the certificates of the TLS listeners from their files, without restarting the server.
+  /// On failure, the listeners keep serving their previous certificates.
+  public func reloadCertificates() throws(KiriError) {
+    try server.reloadCertificates()
   }
 
   public func stop() {
@@ -0,0 +1,60 @@
+import Foundation
+import KiriFFI
+
+/// A cross-origin resource sharing policy enforced by Rust: preflight requests are answered without reaching
+/// a handler, and responses to allowed origins get the `Access-Control-Allow-*` headers browsers expect.
+public struct CORSPolicy: Sendable {
+  /// The allowed origins: `*` for any, an exact origin like `https://example.com`,
+  /// or a pattern where `*` matches any part of the origin, like `https://*.example.com`.
+  public var origins: [String]
+  /// The methods browsers may send.
+  public var methods: [String]
+  /// The request headers browsers may send beyond the CORS-safelisted ones, where `*` allows any.
+  public var headers: [String]
+  /// Whether browsers may send cookies and authorization, and expose the responses to credentialed requests.
+  public var allowCredentials: Bool
+  /// How long browsers may cache the result of a preflight request, or `nil` to leave it to the browser.
+  public var maxAge: TimeInterval?
+
+  public init(
+    origins: [String],
+    methods: [String] = ["GET", "HEAD", "POST"],
+    headers: [String] = [],
+    allowCredentials: Bool = false,
+    maxAge: TimeInterval? = nil
+  ) {
+    self.origins = origins
+    self.methods = methods
+    self.headers = headers
+    self.allowCredentials = allowCredentials
+    self.maxAge = maxAge
+  }
+
+  /// Calls `body` with the comma-separated FFI lists of the policy and its max age in whole seconds.
+  func withRawParts<Result>(
+    _ body: (
+      _ origins: UnsafePointer<UInt8>?, _ originsLength: Int,
+      _ methods: UnsafePointer<UInt8>?, _ methodsLength: Int,
+      _ headers: UnsafePointer<UInt8>?, _ headersLength: Int,
+      _ maxAgeSeconds: UInt64
+    ) -> Result
+  ) -> Result {
+    var origins = self.origins.joined(separator: ",")
+    var methods = self.methods.joined(separator: ",")
+    var headers = self.headers.joined(separator: ",")
+    let maxAgeSeconds = maxAge.map { UInt64(max($0.rounded(), 0)) } ?? 0
+
+    return origins.withUTF8 { origins in
+      methods.withUTF8 { methods in
+        headers.withUTF8 { headers in
+          body(
+            origins.baseAddress, origins.count,
+            methods.baseAddress, methods.count,
+            headers.baseAddress, headers.count,
+            maxAgeSeconds
+          )
+        }
+      }
+    }
+  }
+}
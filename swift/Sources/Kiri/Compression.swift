@@ -0,0 +1,58 @@
+import KiriFFI
+
+/// Compression of responses by Rust, negotiated from the `Accept-Encoding` header of each request.
+/// Responses are compressed when their `Content-Type` is compressible and their body is large enough.
+public struct Compression: Sendable {
+  public enum Encoding: String, Sendable {
+    case zstd
+    case brotli = "br"
+    case gzip
+    /// The zlib format, as specified for `deflate` by HTTP.
+    case deflate
+  }
+
+  /// The encodings offered to clients, by order of preference.
+  public var encodings: [Encoding]
+  /// The compressible media types, or `nil` for common text formats: `text/` matches a type prefix,
+  /// `+json` a suffix, and anything else the exact media type.
+  public var contentTypes: [String]?
+  /// The size in bytes under which bodies are sent uncompressed.
+  public var minimumSize: Int
+
+  public init(encodings: [Encoding] = [.zstd, .brotli, .gzip], contentTypes: [String]? = nil, minimumSize: Int = 1024) {
+    self.encodings = encodings
+    self.contentTypes = contentTypes
+    self.minimumSize = minimumSize
+  }
+
+  /// Calls `body` with the comma-separated FFI lists of the compression, null when left to the defaults.
+  func withRawParts<Result>(
+    _ body: (
+      _ encodings: UnsafePointer<UInt8>?, _ encodingsLength: Int,
+      _ contentTypes: UnsafePointer<UInt8>?, _ contentTypesLength: Int
+    ) -> Result
+  ) -> Result {
+    var encodings = self.encodings.map(\.rawValue).joined(separator: ",")
+
+    return encodings.withUTF8 { encodings in
+      guard var contentTypes = contentTypes?.joined(separator: ",") else {
+        return body(encodings.baseAddress, encodings.count, nil, 0)
+      }
+
+      return contentTypes.withUTF8 { contentTypes in
+        body(encodings.baseAddress, encodings.count, contentTypes.baseAddress, contentTypes.count)
+      }
+    }
+  }
+}
+
+/// Decoding by Rust of request bodies sent with a `gzip`, `deflate`, `br` or `zstd` `Content-Encoding`,
+/// so that handlers receive them decoded. Other encodings are answered with 415 Unsupported Media Type.
+public struct Decompression: Sendable {
+  /// The maximum size in bytes of a decoded body, or `nil` for the body size limit of the route.
+  public var maxSize: UInt64?
+
+  public init(maxSize: UInt64? = nil) {
+    self.maxSize = maxSize
+  }
+}
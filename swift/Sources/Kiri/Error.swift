@@ -1,6 +1,56 @@
+import Foundation
 import KiriFFI
 
-func lastError() -> String? {
+/// A failure reported by the Rust runtime through a `kiri_status` code.
+public struct KiriError: Error, LocalizedError, Sendable {
+  /// Mirrors the `kiri_status` codes documented in `kiri_ffi.h`.
+  public enum Code: UInt32, Sendable {
+    case nullPointer = 1
+    case routerFrozen = 2
+    case invalidUTF8 = 3
+    case invalidArgument = 4
+    case bindFailed = 5
+    case requestNotPending = 6
+    case `internal` = 7
+    case unsupported = 8
+    case tlsFailed = 9
+    case runtimeFailed = 10
+    case unknown = 0xFFFF_FFFF
+  }
+
+  public let code: Code
+  public let message: String
+  /// When a server fails to start, the failure of each listener, keyed by its index in `ServerConfiguration.listeners`.
+  public internal(set) var listenerErrors: [Int: KiriError] = [:]
+
+  public var errorDescription: String? {
+    message
+  }
+
+  init(code: Code, message: String) {
+    self.code = code
+    self.message = message
+  }
+
+  /// Builds the error for a failed call from its status and the last error message of the calling thread.
+  init(status: kiri_status) {
+    self.init(
+      code: Code(rawValue: status.rawValue) ?? .unknown,
+      message: lastErrorMessage() ?? "Unexpected error (\(status.rawValue))"
+    )
+  }
+}
+
+/// Throws the matching `KiriError` if `status` is not `KIRI_STATUS_OK`.
+func check(_ status: kiri_status) throws(KiriError) {
+  guard status != KIRI_STATUS_OK else {
+    return
+  }
+
+  throw KiriError(status: status)
+}
+
+func lastErrorMessage() -> String? {
   guard let message = kiri_last_error_message() else {
     return nil
   }
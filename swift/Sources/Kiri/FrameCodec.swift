@@ -5,6 +5,15 @@ enum FrameCodec {
     let method: UInt8
     let path: String
     let body: Data
+    let version: UInt8
+    let connectionID: UInt64
+    let isSecure: Bool
+    let remoteAddress: SocketAddress?
+    let localAddress: SocketAddress?
+    let clientAddress: SocketAddress?
+    let scheme: String
+    let host: String?
+    let requestID: String
   }
 
   static func decodeRequest(_ data: Data) -> DecodedRequest? {
@@ -15,17 +24,67 @@ enum FrameCodec {
       let v = UInt32(data[i]) | (UInt32(data[i+1])<<8) | (UInt32(data[i+2])<<16) | (UInt32(data[i+3])<<24)
       i += 4; return v
     }
+    func u16() -> UInt16? {
+      guard i+2 <= data.count else { return nil }
+      let v = UInt16(data[i]) | (UInt16(data[i+1])<<8)
+      i += 2; return v
+    }
+    func u64() -> UInt64? {
+      guard let low = u32(), let high = u32() else { return nil }
+      return UInt64(low) | (UInt64(high)<<32)
+    }
     func bytes(_ n: Int) -> Data? { guard i+n <= data.count else { return nil }; defer { i += n }; return data.subdata(in: i..<(i+n)) }
+    func text() -> String? {
+      guard let len = u16(), let textBytes = bytes(Int(len)) else { return nil }
+      return String(data: textBytes, encoding: .utf8)
+    }
+    // Decodes `[u8 kind][u16 text_len][text][u16 port]`, where `.some(nil)` is an unknown address.
+    func address() -> SocketAddress?? {
+      guard let kind = u8(),
+        let textLen = u16(),
+        let textBytes = bytes(Int(textLen)),
+        let text = String(data: textBytes, encoding: .utf8),
+        let port = u16()
+        else { return nil }
+
+      switch kind {
+        case 1: return .some(.ip(host: text, port: port))
+        case 2: return .some(.unix(path: text))
+        default: return .some(nil)
+      }
+    }
 
     guard let method = u8(),
       let pathLen = u32(),
       let pathBytes = bytes(Int(pathLen)),
       let path = String(data: pathBytes, encoding: .utf8),
       let bodyLen = u32(),
-      let body = bytes(Int(bodyLen))
+      let body = bytes(Int(bodyLen)),
+      let version = u8(),
+      let connectionID = u64(),
+      let tls = u8(),
+      let remoteAddress = address(),
+      let localAddress = address(),
+      let clientAddress = address(),
+      let scheme = text(),
+      let host = text(),
+      let requestID = text()
       else { return nil }
 
-    return DecodedRequest(method: method, path: path, body: body)
+    return DecodedRequest(
+      method: method,
+      path: path,
+      body: body,
+      version: version,
+      connectionID: connectionID,
+      isSecure: tls != 0,
+      remoteAddress: remoteAddress,
+      localAddress: localAddress,
+      clientAddress: clientAddress,
+      scheme: scheme,
+      host: host.isEmpty ? nil : host,
+      requestID: requestID
+    )
   }
 
   static func encodeResponse(_ resp: Response) -> Data {
@@ -39,6 +98,23 @@ enum FrameCodec {
     out.append(UInt8((len >> 16) & 0xff))
     out.append(UInt8((len >> 24) & 0xff))
     out.append(resp.body)
+
+    let count = UInt16(min(resp.headers.count, Int(UInt16.max)))
+    out.append(UInt8(count & 0xff))
+    out.append(UInt8((count >> 8) & 0xff))
+    for (name, value) in resp.headers.prefix(Int(count)) {
+      let name = Data(name.utf8), value = Data(value.utf8)
+      let nameLen = UInt16(name.count)
+      out.append(UInt8(nameLen & 0xff))
+      out.append(UInt8((nameLen >> 8) & 0xff))
+      out.append(name)
+      let valueLen = UInt32(value.count)
+      out.append(UInt8(valueLen & 0xff))
+      out.append(UInt8((valueLen >> 8) & 0xff))
+      out.append(UInt8((valueLen >> 16) & 0xff))
+      out.append(UInt8((valueLen >> 24) & 0xff))
+      out.append(value)
+    }
     return out
   }
 }
@@ -0,0 +1,23 @@
+/// The HTTP version of a request, encoded by Rust as `major * 10 + minor`.
+public enum HttpVersion: UInt8, CustomDebugStringConvertible, Sendable {
+  case http0_9 = 9
+  case http1_0 = 10
+  case http1_1 = 11
+  case http2 = 20
+  case http3 = 30
+
+  public var debugDescription: String {
+    switch self {
+      case .http0_9:
+        return "HTTP/0.9"
+      case .http1_0:
+        return "HTTP/1.0"
+      case .http1_1:
+        return "HTTP/1.1"
+      case .http2:
+        return "HTTP/2"
+      case .http3:
+        return "HTTP/3"
+    }
+  }
+}
@@ -5,6 +5,7 @@ fileprivate let logger = Logger(label: "com.kiri-bench.logging-middleware")
 public struct LoggingMiddleware: Middleware {
   public func handle(request: Request, next: (Request) async throws -> Response) async throws -> Response {
     var logger = logger
+    logger[metadataKey: "request.id"] = .string(request.id)
     logger[metadataKey: "request.method"] = .string(request.method.debugDescription)
     logger[metadataKey: "request.url"] = .string(request.path)
 
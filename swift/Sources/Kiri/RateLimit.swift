@@ -0,0 +1,45 @@
+import KiriFFI
+
+/// A token bucket rate limit, enforced by Rust before a request reaches its handler.
+/// Requests finding their bucket empty are answered with 429 Too Many Requests and `RateLimit-*` headers.
+public struct RateLimit: Sendable {
+  /// What requests share a bucket.
+  public enum Key: Sendable {
+    /// One bucket per client IP address.
+    case clientIP
+    /// One bucket per value of the header, e.g. an API key. Requests without it fall back to their client IP.
+    case header(String)
+    /// One bucket per route, shared by all clients.
+    case route
+  }
+
+  public var key: Key
+  /// The number of requests a client can burst before being limited.
+  public var capacity: UInt32
+  /// The number of requests per second refilled into the bucket.
+  public var refillPerSecond: Double
+
+  public init(key: Key = .clientIP, capacity: UInt32, refillPerSecond: Double) {
+    self.key = key
+    self.capacity = capacity
+    self.refillPerSecond = refillPerSecond
+  }
+
+  /// Calls `body` with the FFI arguments of the rate limit.
+  func withRawParts<Result>(
+    _ body: (_ key: UInt8, _ header: UnsafePointer<UInt8>?, _ headerLength: Int) -> Result
+  ) -> Result {
+    switch key {
+      case .clientIP:
+        return body(UInt8(KIRI_RATE_LIMIT_KEY_CLIENT_IP.rawValue), nil, 0)
+
+      case .header(var name):
+        return name.withUTF8 { buffer in
+          body(UInt8(KIRI_RATE_LIMIT_KEY_HEADER.rawValue), buffer.baseAddress, buffer.count)
+        }
+
+      case .route:
+        return body(UInt8(KIRI_RATE_LIMIT_KEY_ROUTE.rawValue), nil, 0)
+    }
+  }
+}
@@ -2,15 +2,41 @@ import Foundation
 
 public struct Request {
   public let method: HttpMethod
+  public let version: HttpVersion
   public let path: String
   public let body: Data
+  /// The address of the client, unknown on Unix domain sockets.
+  public let remoteAddress: SocketAddress?
+  /// The address the client connected to.
+  public let localAddress: SocketAddress?
+  /// Identifies the connection the request was received on, shared by the requests of a keep-alive or HTTP/2 connection.
+  public let connectionID: UInt64
+  /// Whether the request was received over TLS.
+  public let isSecure: Bool
+  /// The address of the client as forwarded by trusted proxies, or `remoteAddress` without them.
+  public let clientAddress: SocketAddress?
+  /// The scheme used by the client, `http` or `https`, as forwarded by trusted proxies.
+  public let scheme: String
+  /// The host requested by the client, as forwarded by trusted proxies.
+  public let host: String?
+  /// Identifies the request across Rust and Swift, echoed in the response header of `ServerConfiguration.requestIDHeader`.
+  public let id: String
   public let cancellation: CancellationToken
 
   init(from decodedRequest: FrameCodec.DecodedRequest, cancellation cancellationToken: CancellationToken) {
     #warning("Make Request throw an error if the HttpMethod could not be initialized.")
     method = HttpMethod(rawValue: decodedRequest.method)!
+    version = HttpVersion(rawValue: decodedRequest.version) ?? .http1_1
     path = decodedRequest.path
     body = decodedRequest.body
+    remoteAddress = decodedRequest.remoteAddress
+    localAddress = decodedRequest.localAddress
+    connectionID = decodedRequest.connectionID
+    isSecure = decodedRequest.isSecure
+    clientAddress = decodedRequest.clientAddress
+    scheme = decodedRequest.scheme
+    host = decodedRequest.host
+    id = decodedRequest.requestID
     cancellation = cancellationToken
   }
 }
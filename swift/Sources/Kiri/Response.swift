@@ -2,15 +2,18 @@ import Foundation
 
 public struct Response {
   public let status: StatusCode
+  /// The response headers, in order. A name may repeat, e.g. `Set-Cookie`.
+  public let headers: [(name: String, value: String)]
   public let body: Data
 
-  public init(status: StatusCode, body: Data) {
+  public init(status: StatusCode, headers: [(name: String, value: String)] = [], body: Data) {
     self.status = status
+    self.headers = headers
     self.body = body
   }
 
   public static func ok(_ text: String) -> Response {
-    Response(status: 200, body: Data(text.utf8))
+    Response(status: 200, headers: [("Content-Type", "text/plain; charset=utf-8")], body: Data(text.utf8))
   }
 
   public static func noContent() -> Response {
@@ -18,6 +21,6 @@ public struct Response {
   }
 
   public static func internalServerError(_ text: String) -> Response {
-    Response(status: 500, body: Data(text.utf8))
+    Response(status: 500, headers: [("Content-Type", "text/plain; charset=utf-8")], body: Data(text.utf8))
   }
 }
@@ -0,0 +1,28 @@
+import Foundation
+
+/// An in-memory cache of the GET responses of the routes opting in with `RouteOptions.cache`.
+/// Fresh responses are served by Rust without calling their handler, according to their `Cache-Control`:
+/// `no-store`, `no-cache` and `private` responses are never cached, nor ones setting a cookie.
+public struct ResponseCache: Sendable {
+  /// The maximum size in bytes of the cached responses, beyond which the least recently used ones are evicted.
+  public var maxSize: Int
+  /// How long responses without `max-age` or `s-maxage` are cached, or `nil` to not cache them.
+  public var defaultTTL: TimeInterval?
+  /// The longest a response is cached, whatever its `Cache-Control`.
+  public var maxTTL: TimeInterval
+  /// The request headers keying the cache besides the method, path and query, e.g. `Accept-Language`.
+  /// Responses whose `Vary` header lists other ones are not cached.
+  public var varyHeaders: [String]
+
+  public init(
+    maxSize: Int = 64 * 1024 * 1024,
+    defaultTTL: TimeInterval? = nil,
+    maxTTL: TimeInterval = 60 * 60,
+    varyHeaders: [String] = []
+  ) {
+    self.maxSize = maxSize
+    self.defaultTTL = defaultTTL
+    self.maxTTL = maxTTL
+    self.varyHeaders = varyHeaders
+  }
+}
@@ -2,40 +2,86 @@ public struct RouteGroup {
   private let router: Router
   private let basePath: String
   private let parentMiddlewares: [Middleware]
+  /// The CORS policy of the routes of the group without their own.
+  private let cors: CORSPolicy?
 
-  init(router: Router, basePath: String, middlewares: [Middleware]) {
+  init(router: Router, basePath: String, middlewares: [Middleware], cors: CORSPolicy? = nil) {
     self.router = router
     self.basePath = basePath
     self.parentMiddlewares = middlewares
+    self.cors = cors
   }
 
-  public func group(_ prefix: String, _ middlewares: Middleware...,  configure: (RouteGroup) -> Void) {
+  /// Registers the routes of `configure` under `prefix`, relative to the group. Their CORS policy is `cors`,
+  /// or the one of the group when `nil`.
+  public func group(
+    _ prefix: String,
+    _ middlewares: Middleware...,
+    cors: CORSPolicy? = nil,
+    configure: (RouteGroup) -> Void
+  ) {
     let nextBase = Path.join(basePath, prefix)
     configure(
       RouteGroup(
         router: router,
         basePath: nextBase,
-        middlewares: parentMiddlewares + middlewares
+        middlewares: parentMiddlewares + middlewares,
+        cors: cors ?? self.cors
       )
     )
   }
 
-  public func get(_ path: String, _ middlewares: Middleware..., handler: @escaping RouteHandler) {
+  public func get(
+    _ path: String,
+    _ middlewares: Middleware...,
+    options: RouteOptions = RouteOptions(),
+    handler: @escaping RouteHandler
+  ) {
     register(
       method: .get,
       path: path,
       middlewares: parentMiddlewares + middlewares,
+      options: options,
       handler: handler
     )
   }
 
-  func register(method: HttpMethod, path: String, middlewares: [Middleware], handler: @escaping RouteHandler) {
+  /// Attaches `layer` to every route of the group. See `Router.layer`.
+  public func layer(_ layer: RustLayer) {
+    router.layer(layer, prefix: basePath)
+  }
+
+  /// Serves the files of `directory` under `prefix`, relative to the group. See `Router.mount`.
+  public func mount(
+    _ prefix: String,
+    directory: String,
+    indexFiles: [String] = ["index.html"],
+    options: RouteOptions = RouteOptions()
+  ) {
+    router.mount(Path.join(basePath, prefix), directory: directory, indexFiles: indexFiles, options: withCORS(options))
+  }
+
+  func register(
+    method: HttpMethod,
+    path: String,
+    middlewares: [Middleware],
+    options: RouteOptions,
+    handler: @escaping RouteHandler
+  ) {
     router.registerGrouped(
       method: method,
       base: basePath,
       path: path,
       middlewares: middlewares,
+      options: withCORS(options),
       handler: handler
     )
   }
+
+  /// Returns `options` with the CORS policy of the group, unless they have their own.
+  private func withCORS(_ options: RouteOptions) -> RouteOptions {
+    var options = options
+    options.cors = options.cors ?? cors
+    return options
+  }
 }
@@ -0,0 +1,36 @@
+/// Settings of a single route, overriding the server-wide ones of `ServerConfiguration`.
+public struct RouteOptions: Sendable {
+  /// The maximum size in bytes of a request body. Larger bodies are answered with 413 Payload Too Large.
+  public var maxBodySize: UInt64?
+  /// The rate limit of the route, replacing `ServerConfiguration.rateLimit`.
+  public var rateLimit: RateLimit?
+  /// Whether responses are compressed when `ServerConfiguration.compression` is set.
+  public var compression: Bool
+  /// Whether 200 responses without an `ETag` are tagged with a hash of their body,
+  /// and answered with 304 Not Modified when the request's `If-None-Match` matches.
+  public var etag: Bool
+  /// Whether GET responses are cached when `ServerConfiguration.cache` is set.
+  public var cache: Bool
+  /// The CORS policy of the route, replacing `ServerConfiguration.cors` and the one of its group.
+  public var cors: CORSPolicy?
+  /// The layers Rust runs around the handler, inside the ones attached to its path with `Router.layer`.
+  public var layers: [RustLayer]
+
+  public init(
+    maxBodySize: UInt64? = nil,
+    rateLimit: RateLimit? = nil,
+    compression: Bool = true,
+    etag: Bool = false,
+    cache: Bool = false,
+    cors: CORSPolicy? = nil,
+    layers: [RustLayer] = []
+  ) {
+    self.maxBodySize = maxBodySize
+    self.rateLimit = rateLimit
+    self.compression = compression
+    self.etag = etag
+    self.cache = cache
+    self.cors = cors
+    self.layers = layers
+  }
+}
@@ -44,6 +44,17 @@ final class RouteRegistry: @unchecked Sendable {
     return id
   }
 
+  /// Allocates an identifier for a route served by Rust, which has no handler.
+  func reserve() -> RouteID {
+    lock.lock()
+    defer {
+      nextId += 1
+      lock.unlock()
+    }
+
+    return nextId
+  }
+
   func entry(for id: RouteID) -> RouteEntry? {
     lock.lock()
     defer {
@@ -26,18 +26,96 @@ public final class Router: @unchecked Sendable {
     RouteRegistry.shared.addGlobal(middleware)
   }
 
-  public func group(_ prefix: String, _ middlewares: Middleware..., configure: (RouteGroup) -> Void) {
+  /// Registers the routes of `configure` under `prefix`, behind `middlewares`, and with the CORS policy `cors`
+  /// unless they have their own.
+  public func group(
+    _ prefix: String,
+    _ middlewares: Middleware...,
+    cors: CORSPolicy? = nil,
+    configure: (RouteGroup) -> Void
+  ) {
     assertMutable()
-    configure(RouteGroup(router: self, basePath: prefix, middlewares: middlewares))
+    configure(RouteGroup(router: self, basePath: prefix, middlewares: middlewares, cors: cors))
   }
 
-  public func register(_ method: HttpMethod, _ path: String, _ middlewares: [Middleware], handler: @escaping RouteHandler) {
+  /// Attaches `layer` to every request whose path lies under `prefix`, whatever route or static mount serves it.
+  /// Requests matching no route still go through it before being answered with 404.
+  /// Layers run in the order they are attached, on requests before their handler, and in reverse order on responses.
+  public func layer(_ layer: RustLayer, prefix: String = "/") {
+    assertMutable()
+
+    var prefix = Path.join("", prefix)
+    let status = prefix.withUTF8 { prefix in
+      layer.withRawParts { name, nameLength, config, configLength in
+        kiri_router_add_layer(_router, prefix.baseAddress, prefix.count, name, nameLength, config, configLength)
+      }
+    }
+
+    do {
+      try check(status)
+    } catch {
+      preconditionFailure("add_layer failed: \(error.code) \(error.message)")
+    }
+  }
+
+  public func register(
+    _ method: HttpMethod,
+    _ path: String,
+    _ middlewares: [Middleware],
+    options: RouteOptions = RouteOptions(),
+    handler: @escaping RouteHandler
+  ) {
     let routeId = RouteRegistry.shared.register(handler, middlewares: middlewares)
     registerRoute(method: method, pattern: path, routeId: routeId)
+    apply(options, to: routeId)
   }
 
-  public func get(_ path: String, _ middlewares: Middleware..., handler: @escaping RouteHandler) {
-    register(.get, path, middlewares, handler: handler)
+  public func get(
+    _ path: String,
+    _ middlewares: Middleware...,
+    options: RouteOptions = RouteOptions(),
+    handler: @escaping RouteHandler
+  ) {
+    register(.get, path, middlewares, options: options, handler: handler)
+  }
+
+  /// Serves the files of `directory` under `prefix` from Rust, without dispatching to Swift, with
+  /// MIME types, `ETag`/`Last-Modified` validators, conditional and range requests.
+  /// Requests to a directory are served the first existing file of `indexFiles`.
+  public func mount(
+    _ prefix: String,
+    directory: String,
+    indexFiles: [String] = ["index.html"],
+    options: RouteOptions = RouteOptions()
+  ) {
+    assertMutable()
+
+    let routeId = RouteRegistry.shared.reserve()
+    var prefix = Path.join("", prefix)
+    var directory = directory
+    var indexFiles = indexFiles.joined(separator: ",")
+
+    let status = prefix.withUTF8 { prefix in
+      directory.withUTF8 { directory in
+        indexFiles.withUTF8 { indexFiles in
+          kiri_router_mount_directory(
+            _router,
+            prefix.baseAddress, prefix.count,
+            directory.baseAddress, directory.count,
+            indexFiles.baseAddress, indexFiles.count,
+            routeId
+          )
+        }
+      }
+    }
+
+    do {
+      try check(status)
+    } catch {
+      preconditionFailure("mount_directory failed: \(error.code) \(error.message)")
+    }
+
+    apply(options, to: routeId)
   }
 
   func registerGrouped(
@@ -45,9 +123,10 @@ public final class Router: @unchecked Sendable {
     base: String,
     path: String,
     middlewares: [Middleware],
+    options: RouteOptions,
     handler: @escaping RouteHandler
   ) {
-    register(method, Path.join(base, path), middlewares, handler: handler)
+    register(method, Path.join(base, path), middlewares, options: options, handler: handler)
   }
 
   func beginStart() {
@@ -79,7 +158,7 @@ public final class Router: @unchecked Sendable {
       return
     }
 
-    let rc: Int32 = patternData.withUnsafeBytes { buffer in
+    let status = patternData.withUnsafeBytes { buffer in
       // Convert the pattern string to a bytes pointer
       let pointer = buffer.bindMemory(to: UInt8.self).baseAddress
 
@@ -92,7 +171,61 @@ public final class Router: @unchecked Sendable {
       )
     }
 
-    precondition(rc == 0, "register_route failed: \(rc)")
+    do {
+      try check(status)
+    } catch {
+      preconditionFailure("register_route failed: \(error.code) \(error.message)")
+    }
+  }
+
+  private func apply(_ options: RouteOptions, to routeId: RouteID) {
+    do {
+      if let maxBodySize = options.maxBodySize {
+        try check(kiri_router_set_route_max_body_size(_router, routeId, maxBodySize))
+      }
+
+      if let rateLimit = options.rateLimit {
+        try check(rateLimit.withRawParts { key, header, headerLength in
+          kiri_router_set_route_rate_limit(
+            _router, routeId,
+            key, header, headerLength,
+            rateLimit.capacity, rateLimit.refillPerSecond
+          )
+        })
+      }
+
+      if !options.compression {
+        try check(kiri_router_set_route_compression(_router, routeId, false))
+      }
+
+      if options.etag {
+        try check(kiri_router_set_route_etag(_router, routeId, true))
+      }
+
+      if options.cache {
+        try check(kiri_router_set_route_cache(_router, routeId, true))
+      }
+
+      if let cors = options.cors {
+        try check(cors.withRawParts { origins, originsLength, methods, methodsLength, headers, headersLength, maxAge in
+          kiri_router_set_route_cors(
+            _router, routeId,
+            origins, originsLength,
+            methods, methodsLength,
+            headers, headersLength,
+            cors.allowCredentials, maxAge
+          )
+        })
+      }
+
+      for layer in options.layers {
+        try check(layer.withRawParts { name, nameLength, config, configLength in
+          kiri_router_add_route_layer(_router, routeId, name, nameLength, config, configLength)
+        })
+      }
+    } catch {
+      preconditionFailure("set route options failed: \(error.code) \(error.message)")
+    }
   }
 
   private func assertMutable(_ function: StaticString = #function) {
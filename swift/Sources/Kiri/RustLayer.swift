@@ -0,0 +1,59 @@
+import KiriFFI
+
+/// A built-in layer run by Rust around the handlers of the routes it is attached to, with `Router.layer`
+/// or `RouteOptions.layers`. Unlike a `Middleware`, it costs no call into Swift, and the requests it answers
+/// never reach a handler.
+public struct RustLayer: Sendable {
+  /// The name of the built-in layer.
+  public var name: String
+  /// The entries configuring the layer, whose keys depend on the layer.
+  public var config: [(key: String, value: String)]
+
+  public init(name: String, config: [(key: String, value: String)] = []) {
+    self.name = name
+    self.config = config
+  }
+
+  /// Sets each header on the responses that do not have it, e.g. security headers.
+  public static func responseHeaders(_ headers: [(name: String, value: String)]) -> RustLayer {
+    RustLayer(name: "response_headers", config: headers.map { (key: $0.name, value: $0.value) })
+  }
+
+  /// Answers clients outside of `allow` when it is not empty, or within `deny`, with 403 Forbidden.
+  /// Both list addresses or CIDR ranges like `10.0.0.0/8`, matched against `Request.clientAddress`.
+  public static func ipFilter(allow: [String] = [], deny: [String] = []) -> RustLayer {
+    var config: [(key: String, value: String)] = []
+    if !allow.isEmpty {
+      config.append((key: "allow", value: allow.joined(separator: ",")))
+    }
+    if !deny.isEmpty {
+      config.append((key: "deny", value: deny.joined(separator: ",")))
+    }
+    return RustLayer(name: "ip_filter", config: config)
+  }
+
+  /// Answers requests whose `header` is not one of `keys` with 401 Unauthorized.
+  public static func apiKey(header: String = "x-api-key", keys: [String]) -> RustLayer {
+    RustLayer(name: "api_key", config: [(key: "header", value: header), (key: "keys", value: keys.joined(separator: ","))])
+  }
+
+  /// Adds the time spent handling each request to a `Server-Timing` header.
+  public static let serverTiming = RustLayer(name: "server_timing")
+
+  /// Calls `body` with the FFI name and `key=value` lines of the layer.
+  func withRawParts<Result>(
+    _ body: (
+      _ name: UnsafePointer<UInt8>?, _ nameLength: Int,
+      _ config: UnsafePointer<UInt8>?, _ configLength: Int
+    ) -> Result
+  ) -> Result {
+    var name = self.name
+    var config = self.config.map { "\($0.key)=\($0.value)" }.joined(separator: "\n")
+
+    return name.withUTF8 { name in
+      config.withUTF8 { config in
+        body(name.baseAddress, name.count, config.baseAddress, config.count)
+      }
+    }
+  }
+}
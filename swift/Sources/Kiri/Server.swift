@@ -1,29 +1,17 @@
 import Foundation
 import KiriFFI
 
-struct ServerError: Error, LocalizedError {
-  let message: String
-
-  var errorDescription: String? {
-    message
-  }
-
-  init(_ message: String) {
-    self.message = message
-  }
-}
-
 final class Server {
-  typealias ServerHandle = UnsafeMutableRawPointer
+  typealias ServerHandle = OpaquePointer
 
-  let port: Port
+  let configuration: ServerConfiguration
 
   private var serverHandle: ServerHandle?
 
   private let router: Router
 
-  init(port: Port, router: Router) {
-    self.port = port
+  init(configuration: ServerConfiguration, router: Router) {
+    self.configuration = configuration
     self.router = router
   }
 
@@ -44,6 +32,50 @@ final class Server {
     router.commitStart()
   }
 
+  /// The addresses the server is listening on, as resolved by the OS.
+  /// Binding port `0` reports the ephemeral port that was assigned.
+  var boundAddresses: [String] {
+    guard let serverHandle else {
+      return []
+    }
+
+    return (0..<kiri_server_bound_address_count(serverHandle)).compactMap { index in
+      var address: UnsafeMutablePointer<CChar>?
+      guard kiri_server_bound_address(serverHandle, index, &address) == KIRI_STATUS_OK, let address else {
+        return nil
+      }
+
+      defer {
+        kiri_string_free(address)
+      }
+
+      return String(cString: address)
+    }
+  }
+
+  /// The counters of the running server, or `nil` when it is not running.
+  var stats: ServerStats? {
+    guard let serverHandle else {
+      return nil
+    }
+
+    var stats = kiri_server_stats()
+    guard kiri_server_get_stats(serverHandle, &stats) == KIRI_STATUS_OK else {
+      return nil
+    }
+
+    return ServerStats(stats)
+  }
+
+  /// Reloads the certificates of the TLS listeners from their files.
+  func reloadCertificates() throws(KiriError) {
+    guard let serverHandle else {
+      return
+    }
+
+    try check(kiri_server_reload_certificates(serverHandle))
+  }
+
   func stop() {
     guard let serverHandle else {
       return
@@ -58,10 +90,16 @@ final class Server {
       return
     }
 
-    serverHandle = kiri_server_start_with_router(port, router._router)
+    let config = try ConfigurationHandle(configuration)
 
-    guard serverHandle != nil else {
-      throw ServerError(lastError() ?? "Unexpected error")
+    var handle: ServerHandle?
+    let status = kiri_server_start_with_config(config._config, router._router, &handle)
+    guard status == KIRI_STATUS_OK else {
+      var error = KiriError(status: status)
+      error.listenerErrors = config.listenerErrors(count: configuration.listeners.count)
+      throw error
     }
+
+    serverHandle = handle
   }
 }
@@ -0,0 +1,425 @@
+import Foundation
+import KiriFFI
+
+/// A TLS certificate chain and its private key, served by a listener.
+public struct Certificate: Sendable {
+  enum Source: Sendable {
+    case files(chain: String, key: String)
+    case pem(chain: Data, key: Data)
+  }
+
+  let source: Source
+  /// The server names (e.g. `example.com` or `*.example.com`) the certificate is selected for through SNI.
+  /// A certificate without names is the default, served when no name matches.
+  public var serverNames: [String]
+
+  /// A certificate read from PEM files when the server starts, and again by `App.reloadCertificates()`.
+  public static func files(chain: String, key: String, serverNames: [String] = []) -> Certificate {
+    Certificate(source: .files(chain: chain, key: key), serverNames: serverNames)
+  }
+
+  /// A certificate from in-memory PEM data.
+  public static func pem(chain: Data, key: Data, serverNames: [String] = []) -> Certificate {
+    Certificate(source: .pem(chain: chain, key: key), serverNames: serverNames)
+  }
+}
+
+/// An address the server listens on.
+public struct Listener: Sendable {
+  public enum Address: Sendable {
+    /// A TCP address. When `host` is `nil`, Rust binds `KIRI_BIND_HOST` if set, or the IPv4 loopback otherwise.
+    case tcp(host: String?, port: Port)
+    /// A Unix domain socket path. A path starting with `@` is bound in the Linux abstract namespace.
+    case unix(path: String)
+  }
+
+  public var address: Address
+  /// Whether an IPv6 listener only accepts IPv6 connections.
+  /// Defaults to `false`, so that `::` serves both IPv4 and IPv6.
+  public var ipv6Only: Bool
+  /// The permission bits applied to a filesystem Unix socket once bound (e.g. `0o660`).
+  public var unixMode: UInt32?
+  /// Whether a Unix socket left behind by a previous process is removed before binding.
+  public var removeStaleSocket: Bool
+  /// The certificates served by the listener. TLS is enabled when at least one is set,
+  /// and requires the Rust library to be built with the `tls` feature.
+  public var certificates: [Certificate]
+  /// Whether connections start with a PROXY protocol v1 or v2 header sent by a load balancer,
+  /// whose source address is then reported as `Request.remoteAddress`. Connections without one are dropped.
+  public var proxyProtocol: Bool
+
+  /// A TCP listener on `host` (an IPv4 or IPv6 address, without brackets) and `port`.
+  /// Port `0` binds an ephemeral port, readable with `App.boundAddresses`.
+  public init(host: String? = nil, port: Port, ipv6Only: Bool = false, proxyProtocol: Bool = false) {
+    self.address = .tcp(host: host, port: port)
+    self.ipv6Only = ipv6Only
+    self.unixMode = nil
+    self.removeStaleSocket = true
+    self.certificates = []
+    self.proxyProtocol = proxyProtocol
+  }
+
+  /// A Unix domain socket listener on `path`.
+  public static func unix(_ path: String, mode: UInt32? = nil, removeStaleSocket: Bool = true) -> Listener {
+    var listener = Listener(port: 0)
+    listener.address = .unix(path: path)
+    listener.unixMode = mode
+    listener.removeStaleSocket = removeStaleSocket
+    return listener
+  }
+}
+
+/// How the Tokio runtime serving the requests is built.
+public struct RuntimeConfiguration: Sendable {
+  /// Runs every task on the server thread instead of a pool of worker threads.
+  public var currentThread: Bool
+  /// The number of worker threads. Defaults to the number of CPU cores.
+  public var workerThreads: Int?
+  /// The maximum number of threads spawned for blocking work.
+  public var maxBlockingThreads: Int?
+  /// The prefix of the thread names, e.g. `api` names them `api-server`, `api-0`, `api-1`…
+  public var threadNamePrefix: String?
+
+  public init(
+    currentThread: Bool = false,
+    workerThreads: Int? = nil,
+    maxBlockingThreads: Int? = nil,
+    threadNamePrefix: String? = nil
+  ) {
+    self.currentThread = currentThread
+    self.workerThreads = workerThreads
+    self.maxBlockingThreads = maxBlockingThreads
+    self.threadNamePrefix = threadNamePrefix
+  }
+}
+
+/// The configuration of a server, applied by Rust when it starts.
+public struct ServerConfiguration: Sendable {
+  /// The addresses the server listens on. All listeners share the same routes.
+  public var listeners: [Listener]
+  /// Whether HTTP/2 is served: through ALPN on TLS listeners, and with prior knowledge (h2c) on cleartext ones.
+  public var http2: Bool
+  public var runtime: RuntimeConfiguration
+  /// The maximum size in bytes of a request body, unless a route sets its own with `RouteOptions.maxBodySize`.
+  /// Larger bodies are answered with 413 Payload Too Large without reaching the handler.
+  public var maxBodySize: UInt64
+  /// The maximum length in bytes of the request target (path and query), answered with 414 URI Too Long beyond it.
+  public var maxURILength: Int
+  /// The maximum size in bytes of the header names and values of a request, answered with 431 beyond it.
+  public var maxHeaderBytes: Int
+  /// The maximum number of header fields of a request, answered with 431 beyond it.
+  /// HTTP/1 connections never accept more than 100 header fields.
+  public var maxHeaderCount: Int
+  /// How long a client has to send the whole body of a request, answered with 408 Request Timeout beyond it.
+  public var bodyReadTimeout: TimeInterval?
+  /// How long an HTTP/1 client has to send the head of a request once it started it, before the connection is closed.
+  public var headerReadTimeout: TimeInterval?
+  /// How long a connection stays open without any request in flight.
+  public var idleTimeout: TimeInterval?
+  /// The number of requests served on a connection before it is gracefully closed.
+  public var maxRequestsPerConnection: UInt64?
+  /// The number of connections open at once across all listeners.
+  /// Beyond it, the server stops accepting until a connection closes.
+  public var maxConnections: Int?
+  /// The number of requests handled by Swift at once.
+  public var maxConcurrentRequests: Int?
+  /// The number of requests waiting for a handler once `maxConcurrentRequests` are in flight.
+  /// Beyond it, requests are answered with 503 Service Unavailable.
+  public var maxQueuedRequests: Int
+  /// Enables adaptive load shedding: while handlers fall behind, requests expected to wait longer than this
+  /// in Swift are answered early with 503 Service Unavailable, protecting the latency of the admitted ones.
+  public var sheddingTargetLatency: TimeInterval?
+  /// The delay, in seconds, clients are asked to wait in the `Retry-After` header of 503 responses.
+  public var retryAfter: UInt64
+  /// The rate limit of every route without its own `RouteOptions.rateLimit`.
+  public var rateLimit: RateLimit?
+  /// The proxies, as addresses or CIDR ranges like `10.0.0.0/8`, whose `Forwarded` and `X-Forwarded-*` headers
+  /// are trusted to resolve `Request.clientAddress`, `scheme`, and `host`.
+  public var trustedProxies: [String]
+  /// Compression of eligible responses, disabled when `nil`.
+  public var compression: Compression?
+  /// Decoding of encoded request bodies, disabled when `nil`: handlers then receive them encoded.
+  public var decompression: Decompression?
+  /// The response cache of the routes opting in with `RouteOptions.cache`, disabled when `nil`.
+  public var cache: ResponseCache?
+  /// The CORS policy of every route without its own `RouteOptions.cors`, disabled when `nil`.
+  public var cors: CORSPolicy?
+  /// The header echoing `Request.id` in every response.
+  public var requestIDHeader: String
+  /// Whether the id sent by clients or proxies in `requestIDHeader` becomes `Request.id`, when it is at most
+  /// 128 visible ASCII characters. Only enable it when a trusted proxy sets or sanitizes the header.
+  public var trustIncomingRequestID: Bool
+
+  public init(
+    listeners: [Listener],
+    http2: Bool = true,
+    runtime: RuntimeConfiguration = RuntimeConfiguration(),
+    maxBodySize: UInt64 = 2 * 1024 * 1024,
+    maxURILength: Int = 8 * 1024,
+    maxHeaderBytes: Int = 32 * 1024,
+    maxHeaderCount: Int = 100,
+    bodyReadTimeout: TimeInterval? = 60,
+    headerReadTimeout: TimeInterval? = 30,
+    idleTimeout: TimeInterval? = 60,
+    maxRequestsPerConnection: UInt64? = nil,
+    maxConnections: Int? = nil,
+    maxConcurrentRequests: Int? = nil,
+    maxQueuedRequests: Int = 1024,
+    sheddingTargetLatency: TimeInterval? = nil,
+    retryAfter: UInt64 = 1,
+    rateLimit: RateLimit? = nil,
+    trustedProxies: [String] = [],
+    compression: Compression? = nil,
+    decompression: Decompression? = nil,
+    cache: ResponseCache? = nil,
+    cors: CORSPolicy? = nil,
+    requestIDHeader: String = "X-Request-Id",
+    trustIncomingRequestID: Bool = false
+  ) {
+    self.listeners = listeners
+    self.http2 = http2
+    self.runtime = runtime
+    self.maxBodySize = maxBodySize
+    self.maxURILength = maxURILength
+    self.maxHeaderBytes = maxHeaderBytes
+    self.maxHeaderCount = maxHeaderCount
+    self.bodyReadTimeout = bodyReadTimeout
+    self.headerReadTimeout = headerReadTimeout
+    self.idleTimeout = idleTimeout
+    self.maxRequestsPerConnection = maxRequestsPerConnection
+    self.maxConnections = maxConnections
+    self.maxConcurrentRequests = maxConcurrentRequests
+    self.maxQueuedRequests = maxQueuedRequests
+    self.sheddingTargetLatency = sheddingTargetLatency
+    self.retryAfter = retryAfter
+    self.rateLimit = rateLimit
+    self.trustedProxies = trustedProxies
+    self.compression = compression
+    self.decompression = decompression
+    self.cache = cache
+    self.cors = cors
+    self.requestIDHeader = requestIDHeader
+    self.trustIncomingRequestID = trustIncomingRequestID
+  }
+
+  public init(port: Port) {
+    self.init(listeners: [Listener(port: port)])
+  }
+}
+
+/// Owns the Rust configuration handle that mirrors a `ServerConfiguration`.
+final class ConfigurationHandle {
+  let _config: UnsafeMutableRawPointer
+
+  init(_ configuration: ServerConfiguration) throws(KiriError) {
+    _config = kiri_server_config_create()
+
+    for listener in configuration.listeners {
+      try add(listener)
+    }
+
+    try check(kiri_server_config_set_http2(_config, configuration.http2))
+    try apply(configuration.runtime)
+    try check(kiri_server_config_set_max_body_size(_config, configuration.maxBodySize))
+    try check(kiri_server_config_set_max_uri_length(_config, configuration.maxURILength))
+    try check(kiri_server_config_set_max_header_bytes(_config, configuration.maxHeaderBytes))
+    try check(kiri_server_config_set_max_header_count(_config, configuration.maxHeaderCount))
+    try check(kiri_server_config_set_body_read_timeout(_config, milliseconds(configuration.bodyReadTimeout)))
+    try check(kiri_server_config_set_header_read_timeout(_config, milliseconds(configuration.headerReadTimeout)))
+    try check(kiri_server_config_set_idle_timeout(_config, milliseconds(configuration.idleTimeout)))
+    try check(kiri_server_config_set_max_requests_per_connection(_config, configuration.maxRequestsPerConnection ?? 0))
+    try check(kiri_server_config_set_max_connections(_config, configuration.maxConnections ?? 0))
+    try check(kiri_server_config_set_max_concurrent_dispatches(_config, configuration.maxConcurrentRequests ?? 0))
+    try check(kiri_server_config_set_max_queued_dispatches(_config, configuration.maxQueuedRequests))
+    try check(kiri_server_config_set_shedding_target_latency(_config, milliseconds(configuration.sheddingTargetLatency)))
+    try check(kiri_server_config_set_retry_after(_config, configuration.retryAfter))
+
+    if let rateLimit = configuration.rateLimit {
+      try check(rateLimit.withRawParts { key, header, headerLength in
+        kiri_server_config_set_rate_limit(
+          _config,
+          key, header, headerLength,
+          rateLimit.capacity, rateLimit.refillPerSecond
+        )
+      })
+    }
+
+    for var proxy in configuration.trustedProxies {
+      try check(proxy.withUTF8 { buffer in
+        kiri_server_config_add_trusted_proxy(_config, buffer.baseAddress, buffer.count)
+      })
+    }
+
+    if let compression = configuration.compression {
+      try check(compression.withRawParts { encodings, encodingsLength, contentTypes, contentTypesLength in
+        kiri_server_config_set_compression(
+          _config,
+          encodings, encodingsLength,
+          contentTypes, contentTypesLength,
+          compression.minimumSize
+        )
+      })
+    }
+
+    if let decompression = configuration.decompression {
+      try check(kiri_server_config_set_request_decompression(_config, decompression.maxSize ?? 0))
+    }
+
+    if let cache = configuration.cache {
+      var varyHeaders = cache.varyHeaders.joined(separator: ",")
+      try check(varyHeaders.withUTF8 { varyHeaders in
+        kiri_server_config_set_response_cache(
+          _config,
+          cache.maxSize,
+          milliseconds(cache.defaultTTL),
+          milliseconds(cache.maxTTL),
+          varyHeaders.baseAddress, varyHeaders.count
+        )
+      })
+    }
+
+    if let cors = configuration.cors {
+      try check(cors.withRawParts { origins, originsLength, methods, methodsLength, headers, headersLength, maxAge in
+        kiri_server_config_set_cors(
+          _config,
+          origins, originsLength,
+          methods, methodsLength,
+          headers, headersLength,
+          cors.allowCredentials, maxAge
+        )
+      })
+    }
+
+    var requestIDHeader = configuration.requestIDHeader
+    try check(requestIDHeader.withUTF8 { header in
+      kiri_server_config_set_request_id(
+        _config,
+        header.baseAddress, header.count,
+        configuration.trustIncomingRequestID
+      )
+    })
+  }
+
+  deinit {
+    kiri_server_config_free(_config)
+  }
+
+  /// Returns the failure of each listener from the last start attempt, keyed by listener index.
+  func listenerErrors(count: Int) -> [Int: KiriError] {
+    var errors: [Int: KiriError] = [:]
+    for index in 0..<count {
+      var message: UnsafeMutablePointer<CChar>?
+      let status = kiri_server_config_listener_error(_config, index, &message)
+      guard status != KIRI_STATUS_OK else {
+        continue
+      }
+
+      defer {
+        kiri_string_free(message)
+      }
+
+      errors[index] = KiriError(
+        code: KiriError.Code(rawValue: status.rawValue) ?? .unknown,
+        message: message.map { String(cString: $0) } ?? "Unexpected error"
+      )
+    }
+
+    return errors
+  }
+
+  /// Converts a timeout to the milliseconds expected by Rust, where `0` disables it.
+  private func milliseconds(_ interval: TimeInterval?) -> UInt64 {
+    guard let interval, interval > 0 else {
+      return 0
+    }
+
+    return UInt64(max((interval * 1_000).rounded(), 1))
+  }
+
+  private func apply(_ runtime: RuntimeConfiguration) throws(KiriError) {
+    try check(kiri_server_config_set_current_thread(_config, runtime.currentThread))
+
+    if let workerThreads = runtime.workerThreads {
+      try check(kiri_server_config_set_worker_threads(_config, workerThreads))
+    }
+
+    if let maxBlockingThreads = runtime.maxBlockingThreads {
+      try check(kiri_server_config_set_max_blocking_threads(_config, maxBlockingThreads))
+    }
+
+    if var prefix = runtime.threadNamePrefix {
+      try check(prefix.withUTF8 { buffer in
+        kiri_server_config_set_thread_name_prefix(_config, buffer.baseAddress, buffer.count)
+      })
+    }
+  }
+
+  private func add(_ listener: Listener) throws(KiriError) {
+    var index = 0
+
+    switch listener.address {
+      case .tcp(let host, let port):
+        if var host {
+          try check(host.withUTF8 { buffer in
+            kiri_server_config_add_tcp_listener(_config, buffer.baseAddress, buffer.count, port, &index)
+          })
+        } else {
+          try check(kiri_server_config_add_tcp_listener(_config, nil, 0, port, &index))
+        }
+
+        try check(kiri_server_config_listener_set_ipv6_only(_config, index, listener.ipv6Only))
+
+      case .unix(var path):
+        try check(path.withUTF8 { buffer in
+          kiri_server_config_add_unix_listener(_config, buffer.baseAddress, buffer.count, &index)
+        })
+
+        if let mode = listener.unixMode {
+          try check(kiri_server_config_listener_set_unix_mode(_config, index, mode))
+        }
+
+        try check(kiri_server_config_listener_set_remove_stale_socket(_config, index, listener.removeStaleSocket))
+    }
+
+    try check(kiri_server_config_listener_set_proxy_protocol(_config, index, listener.proxyProtocol))
+
+    for certificate in listener.certificates {
+      try add(certificate, toListener: index)
+    }
+  }
+
+  private func add(_ certificate: Certificate, toListener index: Int) throws(KiriError) {
+    var serverNames = certificate.serverNames.joined(separator: ",")
+
+    try check(serverNames.withUTF8 { names in
+      // An empty list is passed as null, making the certificate the default one.
+      let namesPointer = certificate.serverNames.isEmpty ? nil : names.baseAddress
+
+      switch certificate.source {
+        case .files(var chain, var key):
+          return chain.withUTF8 { chain in
+            key.withUTF8 { key in
+              kiri_server_config_listener_add_certificate_files(
+                _config, index,
+                chain.baseAddress, chain.count,
+                key.baseAddress, key.count,
+                namesPointer, names.count
+              )
+            }
+          }
+
+        case .pem(let chain, let key):
+          return chain.withUnsafeBytes { chain in
+            key.withUnsafeBytes { key in
+              kiri_server_config_listener_add_certificate_pem(
+                _config, index,
+                chain.bindMemory(to: UInt8.self).baseAddress, chain.count,
+                key.bindMemory(to: UInt8.self).baseAddress, key.count,
+                namesPointer, names.count
+              )
+            }
+          }
+      }
+    })
+  }
+}
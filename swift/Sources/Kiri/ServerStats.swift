@@ -0,0 +1,45 @@
+import Foundation
+import KiriFFI
+
+/// Counters of a running server, read with `App.stats`.
+public struct ServerStats: Sendable {
+  /// Requests answered with 414 URI Too Long.
+  public var rejectedURITooLong: UInt64
+  /// Requests answered with 431 Request Header Fields Too Large.
+  public var rejectedHeadersTooLarge: UInt64
+  /// Requests answered with 413 Payload Too Large.
+  public var rejectedBodyTooLarge: UInt64
+  /// Requests answered with 503 Service Unavailable because too many were waiting for a handler.
+  public var rejectedOverloaded: UInt64
+  /// Requests answered with 503 Service Unavailable by the load shedder.
+  public var rejectedShed: UInt64
+  /// Requests answered with 429 Too Many Requests.
+  public var rejectedRateLimited: UInt64
+  /// The moving average of the time handlers take to respond.
+  public var averageHandlerLatency: TimeInterval
+  /// The connections currently open.
+  public var openConnections: UInt64
+  /// The requests currently handled by Swift.
+  public var inFlightRequests: UInt64
+  /// The requests currently waiting for a handler.
+  public var queuedRequests: UInt64
+  /// Requests of cached routes answered from the response cache, without calling their handler.
+  public var cacheHits: UInt64
+  /// Requests of cached routes that found no fresh response in the cache.
+  public var cacheMisses: UInt64
+
+  init(_ stats: kiri_server_stats) {
+    self.rejectedURITooLong = stats.rejected_uri_too_long
+    self.rejectedHeadersTooLarge = stats.rejected_headers_too_large
+    self.rejectedBodyTooLarge = stats.rejected_body_too_large
+    self.rejectedOverloaded = stats.rejected_overloaded
+    self.rejectedShed = stats.rejected_shed
+    self.rejectedRateLimited = stats.rejected_rate_limited
+    self.averageHandlerLatency = TimeInterval(stats.average_dispatch_latency_us) / 1_000_000
+    self.openConnections = stats.open_connections
+    self.inFlightRequests = stats.in_flight_dispatches
+    self.queuedRequests = stats.queued_dispatches
+    self.cacheHits = stats.cache_hits
+    self.cacheMisses = stats.cache_misses
+  }
+}
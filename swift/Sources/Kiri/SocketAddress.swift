@@ -0,0 +1,16 @@
+/// The address of one end of a connection.
+public enum SocketAddress: Sendable, Equatable, CustomStringConvertible {
+  /// An IPv4 or IPv6 address, without brackets, and a port.
+  case ip(host: String, port: UInt16)
+  /// A Unix domain socket path, starting with `@` in the Linux abstract namespace.
+  case unix(path: String)
+
+  public var description: String {
+    switch self {
+      case .ip(let host, let port):
+        host.contains(":") ? "[\(host)]:\(port)" : "\(host):\(port)"
+      case .unix(let path):
+        "unix:\(path)"
+    }
+  }
+}
@@ -1,26 +1,477 @@
 #pragma once
+
+/* Generated by scripts/generate-header.sh from the kiri_ffi crate. Do not edit manually. */
+
 #include <stdint.h>
 #include <stddef.h>
 #include <stdbool.h>
 
-void* kiri_server_start(uint16_t port);
-void* kiri_server_start_with_router(uint16_t port, void* router);
-void kiri_server_stop(void* handle);
-
-void* kiri_router_create(void);
-void kiri_router_free(void *router);
-int32_t kiri_router_register_route(
-  void* router,
-  uint8_t method,
-  const uint8_t* pattern,
-  size_t pattern_len,
-  uint64_t handler_id
-);
-
-void kiri_request_complete(void* completion_ctx, const uint8_t* resp_ptr, size_t resp_len);
-void kiri_request_free(void *completion_ctx);
-bool kiri_request_is_cancelled(const void *completion_ctx);
-void kiri_cancellation_free(void *completion_ctx);
-
-char* kiri_last_error_message(void);
+// Status code returned by every fallible export of the C API.
+//
+// `KIRI_STATUS_OK` is always `0`; any other value identifies the failure domain.
+// The detailed message of the last failure on the calling thread is available
+// through `kiri_last_error_message`, and its code through `kiri_last_error_code`.
+typedef enum {
+  // The call succeeded.
+  KIRI_STATUS_OK = 0,
+  // A required pointer argument was null.
+  KIRI_STATUS_NULL_POINTER = 1,
+  // The router is frozen because a server was started with it, so it can no longer be mutated.
+  KIRI_STATUS_ROUTER_FROZEN = 2,
+  // A string argument was not valid UTF-8.
+  KIRI_STATUS_INVALID_UTF8 = 3,
+  // An argument had a value outside of its accepted range or format.
+  KIRI_STATUS_INVALID_ARGUMENT = 4,
+  // The server could not bind one of its listener addresses.
+  KIRI_STATUS_BIND_FAILED = 5,
+  // The request was already completed or cancelled, so the completion was discarded.
+  KIRI_STATUS_REQUEST_NOT_PENDING = 6,
+  // An unexpected internal failure, e.g. the server thread exited before reporting its startup.
+  KIRI_STATUS_INTERNAL = 7,
+  // The requested feature was not compiled into this build of the library (e.g. the `tls` cargo feature).
+  KIRI_STATUS_UNSUPPORTED = 8,
+  // A TLS certificate chain or private key could not be loaded.
+  KIRI_STATUS_TLS_FAILED = 9,
+  // The Tokio runtime or the server thread could not be created.
+  KIRI_STATUS_RUNTIME_FAILED = 10,
+} kiri_status;
+
+// What requests share a rate limit bucket, passed as a `uint8_t` to the rate limit setters.
+typedef enum {
+  // One bucket per client IP address.
+  KIRI_RATE_LIMIT_KEY_CLIENT_IP = 0,
+  // One bucket per value of a header, e.g. an API key, falling back to the client IP without it.
+  KIRI_RATE_LIMIT_KEY_HEADER = 1,
+  // One bucket per route, shared by all clients.
+  KIRI_RATE_LIMIT_KEY_ROUTE = 2,
+} kiri_rate_limit_key;
+
+typedef struct kiri_server kiri_server;
+
+typedef uint16_t kiri_port;
+
+typedef uint64_t kiri_handler_id;
+
+// A copy of the counters of a server at a point in time.
+typedef struct {
+  // Requests answered with 414 URI Too Long.
+  uint64_t rejected_uri_too_long;
+  // Requests answered with 431 Request Header Fields Too Large, for their header bytes or count.
+  uint64_t rejected_headers_too_large;
+  // Requests answered with 413 Payload Too Large.
+  uint64_t rejected_body_too_large;
+  // Requests answered with 503 Service Unavailable because the dispatch queue was full.
+  uint64_t rejected_overloaded;
+  // Requests answered with 503 Service Unavailable by the load shedder.
+  uint64_t rejected_shed;
+  // Requests answered with 429 Too Many Requests.
+  uint64_t rejected_rate_limited;
+  // The moving average of the time Swift takes to handle a request, in microseconds.
+  uint64_t average_dispatch_latency_us;
+  // The connections currently open.
+  uint64_t open_connections;
+  // The requests currently dispatched to Swift.
+  uint64_t in_flight_dispatches;
+  // The requests currently waiting for a dispatch slot.
+  uint64_t queued_dispatches;
+  // Requests of cached routes answered from the response cache, without dispatching to Swift.
+  uint64_t cache_hits;
+  // Requests of cached routes that found no fresh response in the cache.
+  uint64_t cache_misses;
+} kiri_server_stats;
+
+// Swift calls this to check if a request has been cancelled.
+bool kiri_request_is_cancelled(const void *context);
+
+void kiri_request_free(const void *context);
+
+void kiri_cancellation_free(const void *context);
+
+// This function is called by the Swift runtime to signal that a request has been completed,
+// by passing the completion context, and the response content.
+// **Must be called exactly once.**
+kiri_status kiri_request_complete(void *completion_ctx, const uint8_t *resp_ptr, size_t resp_len);
+
+void *kiri_server_config_create(void);
+
+void kiri_server_config_free(const void *config);
+
+// Adds a TCP listener on `host` (an IPv4 or IPv6 address, without brackets) and `port`,
+// and writes its index into `out_index`, if not null.
+// A null `host` binds `KIRI_BIND_HOST` if set, or the IPv4 loopback otherwise.
+// Port 0 binds an ephemeral port, readable with `kiri_server_bound_port` once started.
+kiri_status kiri_server_config_add_tcp_listener(const void *config,
+                                                const uint8_t *host_ptr,
+                                                size_t host_len,
+                                                kiri_port port,
+                                                size_t *out_index);
+
+// Adds a Unix domain socket listener on the UTF-8 `path`, and writes its index into `out_index`, if not null.
+// A path starting with `@` binds the rest of the name in the Linux abstract namespace instead of the filesystem.
+// The filesystem socket is removed when the server stops.
+kiri_status kiri_server_config_add_unix_listener(const void *config,
+                                                 const uint8_t *path_ptr,
+                                                 size_t path_len,
+                                                 size_t *out_index);
+
+// Sets the permission bits (e.g. `0660`) applied to the filesystem Unix socket of the listener at `index` once bound.
+kiri_status kiri_server_config_listener_set_unix_mode(const void *config,
+                                                      size_t index,
+                                                      uint32_t mode);
+
+// Sets whether a stale filesystem Unix socket at the path of the listener at `index` is removed before binding.
+// Enabled by default. A socket that still accepts connections is never removed.
+kiri_status kiri_server_config_listener_set_remove_stale_socket(const void *config,
+                                                                size_t index,
+                                                                bool remove_stale_socket);
+
+// Adds a certificate to the listener at `index`, read from the PEM files at the UTF-8 paths
+// `chain_path` (the certificate chain, leaf first) and `key_path` (the private key).
+// The files are read when the server starts, and again by `kiri_server_reload_certificates`.
+// `server_names` is a comma-separated list of names (e.g. `example.com,*.example.com`) the certificate
+// is selected for through SNI; a null `server_names` makes it the default certificate.
+kiri_status kiri_server_config_listener_add_certificate_files(const void *config,
+                                                              size_t index,
+                                                              const uint8_t *chain_path_ptr,
+                                                              size_t chain_path_len,
+                                                              const uint8_t *key_path_ptr,
+                                                              size_t key_path_len,
+                                                              const uint8_t *server_names_ptr,
+                                                              size_t server_names_len);
+
+// Adds a certificate to the listener at `index` from in-memory PEM data,
+// which is copied: `chain` holds the certificate chain (leaf first) and `key` the private key.
+// `server_names` behaves as in `kiri_server_config_listener_add_certificate_files`.
+kiri_status kiri_server_config_listener_add_certificate_pem(const void *config,
+                                                            size_t index,
+                                                            const uint8_t *chain_ptr,
+                                                            size_t chain_len,
+                                                            const uint8_t *key_ptr,
+                                                            size_t key_len,
+                                                            const uint8_t *server_names_ptr,
+                                                            size_t server_names_len);
+
+// Sets whether the IPv6 listener at `index` only accepts IPv6 connections.
+// Listeners are dual-stack by default, so that `::` also accepts IPv4 connections.
+kiri_status kiri_server_config_listener_set_ipv6_only(const void *config,
+                                                      size_t index,
+                                                      bool ipv6_only);
+
+// Sets whether connections to the listener at `index` start with a PROXY protocol v1 or v2 header,
+// whose source address is then reported as the client address. Connections without one are dropped.
+kiri_status kiri_server_config_listener_set_proxy_protocol(const void *config,
+                                                           size_t index,
+                                                           bool proxy_protocol);
+
+// Returns the status of the listener at `index` from the last start attempt with this configuration,
+// writing its error message into `out_message` (or null if it bound successfully).
+// The returned string must be released with `kiri_string_free`.
+kiri_status kiri_server_config_listener_error(const void *config,
+                                              size_t index,
+                                              char **out_message);
+
+// Sets whether the server speaks HTTP/2: through ALPN on TLS listeners,
+// and with prior knowledge (h2c) on cleartext listeners. Enabled by default.
+kiri_status kiri_server_config_set_http2(const void *config, bool enabled);
+
+// Sets whether the server runs every task on its own thread instead of a pool of worker threads.
+kiri_status kiri_server_config_set_current_thread(const void *config, bool current_thread);
+
+// Sets the number of worker threads of the multi-thread runtime. Must be greater than 0.
+kiri_status kiri_server_config_set_worker_threads(const void *config, size_t worker_threads);
+
+// Sets the maximum number of threads spawned for blocking work. Must be greater than 0.
+kiri_status kiri_server_config_set_max_blocking_threads(const void *config,
+                                                        size_t max_blocking_threads);
+
+// Sets the UTF-8 prefix of the names of the server threads, e.g. `api` names them `api-server`, `api-0`, `api-1`…
+kiri_status kiri_server_config_set_thread_name_prefix(const void *config,
+                                                      const uint8_t *prefix_ptr,
+                                                      size_t prefix_len);
+
+// Sets the maximum size in bytes of a request body, 2 MiB by default.
+// Larger bodies are answered with 413 Payload Too Large without being dispatched.
+kiri_status kiri_server_config_set_max_body_size(const void *config, uint64_t max_body_size);
+
+// Sets the maximum length in bytes of the request target (path and query), 8 KiB by default.
+// Longer targets are answered with 414 URI Too Long.
+kiri_status kiri_server_config_set_max_uri_length(const void *config, size_t max_uri_length);
+
+// Sets the maximum size in bytes of the header names and values of a request, 32 KiB by default.
+// Larger headers are answered with 431 Request Header Fields Too Large.
+kiri_status kiri_server_config_set_max_header_bytes(const void *config, size_t max_header_bytes);
+
+// Sets the maximum number of header fields of a request, 100 by default.
+// More fields are answered with 431 Request Header Fields Too Large.
+kiri_status kiri_server_config_set_max_header_count(const void *config, size_t max_header_count);
+
+// Sets how long a client has to send the whole body of a request, 60 seconds by default.
+// Slower bodies are answered with 408 Request Timeout. `0` disables the timeout.
+kiri_status kiri_server_config_set_body_read_timeout(const void *config, uint64_t timeout_ms);
+
+// Sets how long an HTTP/1 client has to send the head of a request once it started it,
+// 30 seconds by default. The connection is closed beyond it. `0` disables the timeout.
+kiri_status kiri_server_config_set_header_read_timeout(const void *config, uint64_t timeout_ms);
+
+// Sets how long a connection stays open without any request in flight, 60 seconds by default.
+// `0` disables the timeout.
+kiri_status kiri_server_config_set_idle_timeout(const void *config, uint64_t timeout_ms);
+
+// Sets the number of requests served on a connection before it is gracefully closed.
+// `0`, the default, serves any number of requests.
+kiri_status kiri_server_config_set_max_requests_per_connection(const void *config,
+                                                               uint64_t max_requests);
+
+// Sets the number of connections open at once across all listeners. Beyond it, the server stops
+// accepting until a connection closes. `0`, the default, accepts any number of connections.
+// Values above `usize::MAX >> 3` are rejected with `KIRI_STATUS_INVALID_ARGUMENT`.
+kiri_status kiri_server_config_set_max_connections(const void *config, size_t max_connections);
+
+// Sets the number of requests dispatched to Swift at once. `0`, the default, dispatches any number.
+// Values above `usize::MAX >> 3` are rejected with `KIRI_STATUS_INVALID_ARGUMENT`.
+kiri_status kiri_server_config_set_max_concurrent_dispatches(const void *config,
+                                                             size_t max_concurrent);
+
+// Sets the number of requests waiting for a dispatch slot, 1024 by default.
+// Beyond it, requests are answered with 503 Service Unavailable. `0` rejects any request without a free slot.
+kiri_status kiri_server_config_set_max_queued_dispatches(const void *config,
+                                                         size_t max_queued);
+
+// Enables adaptive load shedding: requests expected to spend longer than `target_latency_ms` in Swift,
+// including the time waiting for a dispatch slot, are answered with 503 Service Unavailable.
+// `0`, the default, disables load shedding.
+kiri_status kiri_server_config_set_shedding_target_latency(const void *config,
+                                                           uint64_t target_latency_ms);
+
+// Sets the delay sent in the `Retry-After` header of 503 responses, in seconds. 1 second by default.
+kiri_status kiri_server_config_set_retry_after(const void *config,
+                                               uint64_t retry_after_secs);
+
+// Rate limits every route without its own rate limit with a token bucket of `capacity` tokens,
+// refilled at `refill_per_second`. Requests finding their bucket empty are answered with
+// 429 Too Many Requests without being dispatched. `key` is a `kiri_rate_limit_key`,
+// and `header_ptr` names the header of `KIRI_RATE_LIMIT_KEY_HEADER`.
+kiri_status kiri_server_config_set_rate_limit(const void *config,
+                                              uint8_t key,
+                                              const uint8_t *header_ptr,
+                                              size_t header_len,
+                                              uint32_t capacity,
+                                              double refill_per_second);
+
+// Trusts the `Forwarded` and `X-Forwarded-*` headers sent by the proxies in the UTF-8 range `cidr`,
+// e.g. `10.0.0.0/8` or a single address, to resolve the client address, scheme and host of requests.
+kiri_status kiri_server_config_add_trusted_proxy(const void *config,
+                                                 const uint8_t *cidr_ptr,
+                                                 size_t cidr_len);
+
+// Compresses the responses of routes not opting out whose body is at least `min_size` bytes, and whose
+// `Content-Type` matches one of `content_types`. `encodings` lists the offered encodings among `zstd`,
+// `br`, `gzip` and `deflate` by order of preference, e.g. `br,gzip`. Both are comma-separated UTF-8 lists,
+// where a null pointer keeps the defaults: all three encodings, and common text formats.
+kiri_status kiri_server_config_set_compression(const void *config,
+                                               const uint8_t *encodings_ptr,
+                                               size_t encodings_len,
+                                               const uint8_t *content_types_ptr,
+                                               size_t content_types_len,
+                                               size_t min_size);
+
+// Decodes request bodies sent with a `gzip`, `deflate`, `br` or `zstd` `Content-Encoding` before
+// dispatching them, answering other encodings with 415 Unsupported Media Type. Decoded bodies larger than
+// `max_size` bytes are answered with 413 Payload Too Large, where `0` applies the body size limit of the route.
+kiri_status kiri_server_config_set_request_decompression(const void *config,
+                                                         uint64_t max_size);
+
+// Caches the GET responses of the routes opting in, up to `max_size` bytes, and serves them without dispatching
+// to Swift while fresh. Responses are fresh for their `s-maxage` or `max-age`, or `default_ttl_ms` without either,
+// where `0` does not cache them, and for at most `max_ttl_ms`, where `0` keeps the default of one hour.
+// `vary_headers` is a comma-separated UTF-8 list of the request headers keying the cache besides the method,
+// path and query, e.g. `accept-language`. Responses whose `Vary` lists other headers are not cached.
+kiri_status kiri_server_config_set_response_cache(const void *config,
+                                                  size_t max_size,
+                                                  uint64_t default_ttl_ms,
+                                                  uint64_t max_ttl_ms,
+                                                  const uint8_t *vary_headers_ptr,
+                                                  size_t vary_headers_len);
+
+// Applies a CORS policy to every route without its own: preflight `OPTIONS` requests are answered by Rust, and
+// the responses to allowed origins get `Access-Control-Allow-*` headers. `origins` lists the allowed origins,
+// where `*` allows any and matches any part of a pattern like `https://*.example.com`. `methods` lists the allowed
+// methods, where a null pointer means `GET,HEAD,POST`, and `headers` the allowed request headers, where `*` allows
+// any. All three are comma-separated UTF-8 lists. `max_age_secs` is how long browsers may cache a preflight,
+// where `0` leaves it to the browser.
+kiri_status kiri_server_config_set_cors(const void *config,
+                                        const uint8_t *origins_ptr,
+                                        size_t origins_len,
+                                        const uint8_t *methods_ptr,
+                                        size_t methods_len,
+                                        const uint8_t *headers_ptr,
+                                        size_t headers_len,
+                                        bool credentials,
+                                        uint64_t max_age_secs);
+
+// Sets how requests are identified. Every request gets an id, included in its request frame and echoed in
+// the UTF-8 `header` of its response, where a null pointer keeps `x-request-id`. With `trust_incoming`, the id
+// sent in `header` by the client or a proxy in front of the server is used instead of a generated one, when it
+// is at most 128 visible ASCII characters.
+kiri_status kiri_server_config_set_request_id(const void *config,
+                                              const uint8_t *header_ptr,
+                                              size_t header_len,
+                                              bool trust_incoming);
+
+// Returns the status of the most recent failure on the calling thread, or `Ok` if no call failed on it yet.
+// Successful calls leave it as it is, so it only describes the call that just returned an error status.
+kiri_status kiri_last_error_code(void);
+
+// Returns a copy of the message of the most recent failure on the calling thread,
+// or null if no call failed on it yet. Like `kiri_last_error_code`, it is not cleared by successful calls.
+// The returned string must be released with `kiri_last_error_message_free`.
+char *kiri_last_error_message(void);
+
 void kiri_last_error_message_free(char *s);
+
+void *kiri_router_create(void);
+
+void kiri_router_free(const void *router);
+
+// Registers a route matching `method` and the UTF-8 `pattern`, dispatched to `handler_id`.
+// Fails with `RouterFrozen` once a server has been started with the router.
+kiri_status kiri_router_register_route(const void *router,
+                                       uint8_t method,
+                                       const uint8_t *pattern_ptr,
+                                       size_t pattern_len,
+                                       kiri_handler_id handler_id);
+
+// Mounts the directory at the UTF-8 path `root` under the UTF-8 path `prefix`, served by Rust without
+// dispatching to Swift. Requests to a directory are served its first existing file among the comma-separated
+// `index_files`, where a null pointer means `index.html`. `handler_id` identifies the mount for the
+// `kiri_router_set_route_*` options, and is never dispatched.
+// Fails with `RouterFrozen` once a server has been started with the router.
+kiri_status kiri_router_mount_directory(const void *router,
+                                        const uint8_t *prefix_ptr,
+                                        size_t prefix_len,
+                                        const uint8_t *root_ptr,
+                                        size_t root_len,
+                                        const uint8_t *index_files_ptr,
+                                        size_t index_files_len,
+                                        kiri_handler_id handler_id);
+
+// Sets the maximum size in bytes of the request bodies accepted by the routes of `handler_id`,
+// overriding the server-wide limit.
+kiri_status kiri_router_set_route_max_body_size(const void *router,
+                                                kiri_handler_id handler_id,
+                                                uint64_t max_body_size);
+
+// Rate limits the routes of `handler_id` with their own token bucket, replacing the server-wide rate limit.
+// See `kiri_server_config_set_rate_limit`.
+kiri_status kiri_router_set_route_rate_limit(const void *router,
+                                             kiri_handler_id handler_id,
+                                             uint8_t key,
+                                             const uint8_t *header_ptr,
+                                             size_t header_len,
+                                             uint32_t capacity,
+                                             double refill_per_second);
+
+// Sets whether the responses of the routes of `handler_id` are compressed when the server compresses
+// responses. Routes are compressed by default.
+kiri_status kiri_router_set_route_compression(const void *router,
+                                              kiri_handler_id handler_id,
+                                              bool enabled);
+
+// Sets whether the 200 responses of the routes of `handler_id` that have no `ETag` are tagged with
+// a strong `ETag` hashed from their body, and answered with 304 Not Modified when `If-None-Match` matches.
+kiri_status kiri_router_set_route_etag(const void *router,
+                                       kiri_handler_id handler_id,
+                                       bool enabled);
+
+// Sets whether the GET responses of the routes of `handler_id` are cached, when the server has a response cache.
+kiri_status kiri_router_set_route_cache(const void *router,
+                                        kiri_handler_id handler_id,
+                                        bool enabled);
+
+// Applies a CORS policy to the routes of `handler_id`, replacing the server-wide one.
+// See `kiri_server_config_set_cors`.
+kiri_status kiri_router_set_route_cors(const void *router,
+                                       kiri_handler_id handler_id,
+                                       const uint8_t *origins_ptr,
+                                       size_t origins_len,
+                                       const uint8_t *methods_ptr,
+                                       size_t methods_len,
+                                       const uint8_t *headers_ptr,
+                                       size_t headers_len,
+                                       bool credentials,
+                                       uint64_t max_age_secs);
+
+// Attaches the built-in layer `name`, configured by the UTF-8 `config`, to every request whose path lies under
+// the UTF-8 path `prefix`, whatever route or static mount serves it, including the ones answered with 404 for
+// lack of a route, where `/` means all requests.
+// A `:param` segment of the prefix matches any segment, and paths are compared once percent-decoded.
+// Layers run in the order they are attached, those of prefixes before those of routes, on the request before
+// it is dispatched, and in reverse order on its response. `config` holds lines of `key=value` whose keys
+// depend on the layer, where a null pointer means no line:
+//
+// - `response_headers`: each line is a header set on responses that do not have it.
+// - `ip_filter`: `allow` and `deny` list client addresses or CIDR ranges, answering others with 403 Forbidden.
+// - `api_key`: answers requests whose `header` (`x-api-key` by default) is not one of `keys` with 401 Unauthorized.
+// - `server_timing`: adds the time spent handling the request to a `Server-Timing` header.
+//
+// Fails with `RouterFrozen` once a server has been started with the router.
+kiri_status kiri_router_add_layer(const void *router,
+                                  const uint8_t *prefix_ptr,
+                                  size_t prefix_len,
+                                  const uint8_t *name_ptr,
+                                  size_t name_len,
+                                  const uint8_t *config_ptr,
+                                  size_t config_len);
+
+// Attaches the built-in layer `name` to the routes of `handler_id`, inside the layers of their prefixes.
+// See `kiri_router_add_layer`.
+kiri_status kiri_router_add_route_layer(const void *router,
+                                        kiri_handler_id handler_id,
+                                        const uint8_t *name_ptr,
+                                        size_t name_len,
+                                        const uint8_t *config_ptr,
+                                        size_t config_len);
+
+// Starts the server with empty routes and writes the server handle into `out_handle`.
+// Available for backwards compatibility, with the limits of `kiri_server_start_with_router`.
+kiri_status kiri_server_start(kiri_port port, kiri_server **out_handle);
+
+// Starts the server and writes the server handle into `out_handle`.
+// Request bodies are not limited in size, and connections have no header-read, body-read or idle timeout:
+// use `kiri_server_start_with_config` for the default limits.
+kiri_status kiri_server_start_with_router(kiri_port port,
+                                          const void *router,
+                                          kiri_server **out_handle);
+
+// Starts the server with the listeners and options of `config`, and writes the server handle into `out_handle`.
+// The configuration is copied, so it can be released or reused once this returns.
+// When listeners fail to bind, each failure is readable with `kiri_server_config_listener_error`.
+kiri_status kiri_server_start_with_config(const void *config,
+                                          const void *router,
+                                          kiri_server **out_handle);
+
+// Stops the server managed by the passed handle, and releases the handle.
+kiri_status kiri_server_stop(kiri_server *handle);
+
+// Returns the number of addresses the server is listening on.
+size_t kiri_server_bound_address_count(const kiri_server *handle);
+
+// Writes the address at `index` the server is listening on into `out_address`,
+// formatted as `ip:port` for TCP listeners, and `unix:path` or `unix:@name` for Unix listeners.
+// The returned string must be released with `kiri_string_free`.
+kiri_status kiri_server_bound_address(const kiri_server *handle, size_t index, char **out_address);
+
+// Writes the port of the address at `index` the server is listening on into `out_port`.
+kiri_status kiri_server_bound_port(const kiri_server *handle, size_t index, kiri_port *out_port);
+
+// Reloads the certificates of every TLS listener of the server from their files,
+// so that renewed certificates are served to new connections without a restart.
+// In-memory certificates are kept as they are. If any certificate fails to load,
+// the listener keeps serving its previous certificates and the error is returned.
+kiri_status kiri_server_reload_certificates(const kiri_server *handle);
+
+// Writes the current counters of the server into `out_stats`.
+kiri_status kiri_server_get_stats(const kiri_server *handle, kiri_server_stats *out_stats);
+
+// Releases a string returned by the C API.
+void kiri_string_free(char *s);